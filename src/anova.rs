@@ -0,0 +1,232 @@
+use crate::statistics::regularized_incomplete_beta;
+use crate::{Condition, Sex};
+
+/// The order of each participant's cell means.
+pub const CELLS: [(Condition, Sex); 6] = [
+    (Condition::Angry, Sex::Male),
+    (Condition::Happy, Sex::Male),
+    (Condition::Neutral, Sex::Male),
+    (Condition::Angry, Sex::Female),
+    (Condition::Happy, Sex::Female),
+    (Condition::Neutral, Sex::Female),
+];
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct AnovaEffect {
+    pub name: &'static str,
+    pub effect_degrees_of_freedom: f64,
+    pub error_degrees_of_freedom: f64,
+    pub f: f64,
+    pub p: f64,
+    /// 1 for effects with a single degree of freedom, where sphericity
+    /// holds trivially.
+    pub greenhouse_geisser_epsilon: f64,
+    pub greenhouse_geisser_p: f64,
+    pub partial_eta_squared: f64,
+}
+
+/// Upper tail probability of the F distribution.
+pub fn f_distribution_survival(f: f64, numerator: f64, denominator: f64) -> f64 {
+    if f <= 0. {
+        return 1.;
+    }
+    regularized_incomplete_beta(
+        denominator / 2.,
+        numerator / 2.,
+        denominator / (denominator + numerator * f),
+    )
+}
+
+// Orthonormal contrasts over the three emotions and the two face sexes, and
+// the normalized weights that average over them.
+fn emotion_contrasts() -> [[f64; 3]; 2] {
+    let pair = 1. / 2f64.sqrt();
+    let triple = 1. / 6f64.sqrt();
+    [[pair, -pair, 0.], [triple, triple, -2. * triple]]
+}
+
+fn emotion_mean() -> [f64; 3] {
+    [1. / 3f64.sqrt(); 3]
+}
+
+fn sex_contrast() -> [f64; 2] {
+    [1. / 2f64.sqrt(), -1. / 2f64.sqrt()]
+}
+
+fn sex_mean() -> [f64; 2] {
+    [1. / 2f64.sqrt(); 2]
+}
+
+/// Contrast weights over `CELLS` from separate weights over emotions and
+/// face sexes.
+fn cell_weights(emotion: &[f64; 3], sex: &[f64; 2]) -> [f64; 6] {
+    let mut weights = [0.; 6];
+    for (sex_index, sex_weight) in sex.iter().enumerate() {
+        for (emotion_index, emotion_weight) in emotion.iter().enumerate() {
+            weights[sex_index * 3 + emotion_index] = emotion_weight * sex_weight;
+        }
+    }
+    weights
+}
+
+fn effect(name: &'static str, contrasts: &[[f64; 6]], cell_means: &[[f64; 6]]) -> AnovaEffect {
+    let participants = cell_means.len() as f64;
+    let degrees_of_freedom = contrasts.len();
+    let scores = cell_means
+        .iter()
+        .map(|means| {
+            contrasts
+                .iter()
+                .map(|weights| weights.iter().zip(means).map(|(w, m)| w * m).sum::<f64>())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let score_means = (0..degrees_of_freedom)
+        .map(|j| scores.iter().map(|score| score[j]).sum::<f64>() / participants)
+        .collect::<Vec<_>>();
+    let covariance = (0..degrees_of_freedom)
+        .map(|j| {
+            (0..degrees_of_freedom)
+                .map(|k| {
+                    scores
+                        .iter()
+                        .map(|score| (score[j] - score_means[j]) * (score[k] - score_means[k]))
+                        .sum::<f64>()
+                        / (participants - 1.)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let sum_of_squares_effect =
+        participants * score_means.iter().map(|mean| mean * mean).sum::<f64>();
+    let trace = (0..degrees_of_freedom)
+        .map(|j| covariance[j][j])
+        .sum::<f64>();
+    let sum_of_squares_error = (participants - 1.) * trace;
+    let trace_of_square = covariance
+        .iter()
+        .flatten()
+        .map(|value| value * value)
+        .sum::<f64>();
+    let effect_degrees_of_freedom = degrees_of_freedom as f64;
+    let error_degrees_of_freedom = effect_degrees_of_freedom * (participants - 1.);
+    let f = (sum_of_squares_effect / effect_degrees_of_freedom)
+        / (sum_of_squares_error / error_degrees_of_freedom);
+    let greenhouse_geisser_epsilon = if degrees_of_freedom == 1 {
+        1.
+    } else {
+        trace * trace / (effect_degrees_of_freedom * trace_of_square)
+    };
+    AnovaEffect {
+        name,
+        effect_degrees_of_freedom,
+        error_degrees_of_freedom,
+        f,
+        p: f_distribution_survival(f, effect_degrees_of_freedom, error_degrees_of_freedom),
+        greenhouse_geisser_epsilon,
+        greenhouse_geisser_p: f_distribution_survival(
+            f,
+            greenhouse_geisser_epsilon * effect_degrees_of_freedom,
+            greenhouse_geisser_epsilon * error_degrees_of_freedom,
+        ),
+        partial_eta_squared: sum_of_squares_effect / (sum_of_squares_effect + sum_of_squares_error),
+    }
+}
+
+/// The 3 (emotion) x 2 (face sex) within-subject ANOVA over each
+/// participant's cell means, ordered as in `CELLS`. Participants with any
+/// non-finite cell mean are left out. `None` with fewer than two
+/// participants remaining.
+pub fn emotion_by_sex_anova(cell_means: &[[f64; 6]]) -> Option<Vec<AnovaEffect>> {
+    let complete = cell_means
+        .iter()
+        .filter(|means| means.iter().all(|mean| mean.is_finite()))
+        .copied()
+        .collect::<Vec<_>>();
+    if complete.len() < 2 {
+        return None;
+    }
+    Some(vec![
+        effect(
+            "Emotion",
+            &emotion_contrasts().map(|emotion| cell_weights(&emotion, &sex_mean())),
+            &complete,
+        ),
+        effect(
+            "Sex",
+            &[cell_weights(&emotion_mean(), &sex_contrast())],
+            &complete,
+        ),
+        effect(
+            "Emotion x Sex",
+            &emotion_contrasts().map(|emotion| cell_weights(&emotion, &sex_contrast())),
+            &complete,
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    fn round(value: f64) -> f64 {
+        (value * 1e4).round() / 1e4
+    }
+
+    fn cell_means() -> Vec<[f64; 6]> {
+        vec![
+            [620., 600., 610., 640., 605., 625.],
+            [700., 650., 690., 720., 660., 700.],
+            [560., 555., 575., 590., 550., 570.],
+            [650., 610., 640., 660., 640., 655.],
+            [590., 600., 585., 630., 580., 600.],
+            [680., 640., 650., 700., 655., 690.],
+        ]
+    }
+
+    #[test]
+    fn f_distribution_survival() {
+        assert_eq!(
+            0.05,
+            round(crate::anova::f_distribution_survival(4.964603, 1., 10.))
+        );
+        assert_eq!(1., crate::anova::f_distribution_survival(0., 2., 10.));
+    }
+
+    #[test]
+    fn emotion_by_sex_anova() {
+        let effects = crate::anova::emotion_by_sex_anova(&cell_means()).unwrap();
+        let summary = effects
+            .iter()
+            .map(|effect| {
+                (
+                    effect.name,
+                    effect.effect_degrees_of_freedom,
+                    effect.error_degrees_of_freedom,
+                    round(effect.f),
+                    round(effect.partial_eta_squared),
+                    round(effect.greenhouse_geisser_epsilon),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("Emotion", 2., 10., 26.894, 0.8432, 0.6902),
+                ("Sex", 1., 5., 33.0471, 0.8686, 1.),
+                ("Emotion x Sex", 2., 10., 1.8658, 0.2718, 0.7858),
+            ],
+            summary
+        );
+        assert!(effects[0].p < effects[0].greenhouse_geisser_p);
+        assert_eq!(effects[1].p, effects[1].greenhouse_geisser_p);
+    }
+
+    #[test]
+    fn incomplete_participants_left_out() {
+        let mut means = cell_means();
+        means.push([f64::NAN, 600., 610., 640., 605., 625.]);
+        assert_eq!(
+            crate::anova::emotion_by_sex_anova(&cell_means()),
+            crate::anova::emotion_by_sex_anova(&means)
+        );
+        assert_eq!(None, crate::anova::emotion_by_sex_anova(&means[..1]));
+    }
+}
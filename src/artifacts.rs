@@ -0,0 +1,142 @@
+use crate::Trial;
+
+// BESA event codes for the start and end of a marked artifact segment.
+const ARTIFACT_ON_CODE: i32 = 21;
+const ARTIFACT_OFF_CODE: i32 = 22;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ArtifactSegment {
+    pub start_time_microseconds: i64,
+    pub end_time_microseconds: i64,
+}
+
+/// Reads the artifact on/off markers from a BESA .evt file. An artifact that
+/// is never switched off runs to the end of the recording.
+pub fn parse_artifact_segments(input: &str) -> Vec<ArtifactSegment> {
+    let mut segments = Vec::new();
+    let mut start_time_microseconds = None;
+    for line in input.lines() {
+        let tokens = line.split_whitespace().collect::<Vec<&str>>();
+        if tokens.len() < 2 {
+            continue;
+        }
+        let (Ok(time_microseconds), Ok(code)) =
+            (tokens[0].parse::<i64>(), tokens[1].parse::<i32>())
+        else {
+            continue;
+        };
+        match code {
+            ARTIFACT_ON_CODE => {
+                start_time_microseconds.get_or_insert(time_microseconds);
+            }
+            ARTIFACT_OFF_CODE => {
+                if let Some(start) = start_time_microseconds.take() {
+                    segments.push(ArtifactSegment {
+                        start_time_microseconds: start,
+                        end_time_microseconds: time_microseconds,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = start_time_microseconds {
+        segments.push(ArtifactSegment {
+            start_time_microseconds: start,
+            end_time_microseconds: i64::MAX,
+        });
+    }
+    segments
+}
+
+/// Marks trials whose stimulus-to-response window overlaps an artifact
+/// segment. Trials without a button press are checked at stimulus onset only.
+pub fn flag_artifacts(trials: &mut [Trial], segments: &[ArtifactSegment]) {
+    for trial in trials.iter_mut() {
+        let window_start = trial.stimulus_time_microseconds;
+        let window_end = trial
+            .button_press_time_microseconds
+            .unwrap_or(trial.stimulus_time_microseconds);
+        trial.artifact = segments.iter().any(|segment| {
+            segment.start_time_microseconds <= window_end
+                && window_start <= segment.end_time_microseconds
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ArtifactSegment;
+    use crate::{Condition, Sex, Trial};
+
+    fn trial(
+        stimulus_time_microseconds: i64,
+        button_press_time_microseconds: Option<i64>,
+    ) -> Trial {
+        Trial {
+            correct_response: true,
+            condition: Condition::Angry,
+            sex: Sex::Male,
+            response_time_milliseconds: None,
+            stimulus_time_microseconds,
+            button_press_time_microseconds,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_artifact_segments() {
+        let segments = crate::artifacts::parse_artifact_segments(
+            "Tmu         	Code	TriNo	Comnt	Ver-C
+3809479        	11	0	all  6017 0.874 2.19
+4618000        	1	4096	FIFF Trigger: 4096
+5000000        	21	0	Artifact on
+5250000        	22	0	Artifact off
+6293000        	1	22	FIFF Trigger: 22
+9000000        	21	0	Artifact on                             ",
+        );
+        assert_eq!(
+            vec![
+                ArtifactSegment {
+                    start_time_microseconds: 5000000,
+                    end_time_microseconds: 5250000
+                },
+                ArtifactSegment {
+                    start_time_microseconds: 9000000,
+                    end_time_microseconds: i64::MAX
+                }
+            ],
+            segments
+        );
+    }
+
+    #[test]
+    fn flag_artifacts() {
+        let mut trials = vec![
+            trial(1_000_000, Some(1_600_000)),
+            trial(3_000_000, Some(3_500_000)),
+            trial(5_000_000, None),
+            trial(7_000_000, Some(7_400_000)),
+        ];
+        crate::artifacts::flag_artifacts(
+            &mut trials,
+            &[
+                ArtifactSegment {
+                    start_time_microseconds: 1_500_000,
+                    end_time_microseconds: 2_000_000,
+                },
+                ArtifactSegment {
+                    start_time_microseconds: 4_000_000,
+                    end_time_microseconds: 5_000_000,
+                },
+            ],
+        );
+        assert_eq!(
+            vec![true, false, true, false],
+            trials
+                .iter()
+                .map(|trial| trial.artifact)
+                .collect::<Vec<_>>()
+        );
+    }
+}
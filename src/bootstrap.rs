@@ -0,0 +1,194 @@
+use crate::anova::CELLS;
+use crate::statistics::percentile;
+use crate::Trial;
+
+/// SplitMix64, small and fast, so resampling is reproducible from a seed
+/// without pulling in a random number crate.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform index below `bound`, by rejection so there is no modulo bias.
+    pub fn below(&mut self, bound: usize) -> usize {
+        let bound = bound as u64;
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return (value % bound) as usize;
+            }
+        }
+    }
+
+    /// Uniform in the open interval (0, 1), so safe to take the log of.
+    pub fn uniform(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Bootstrap {
+    pub resamples: usize,
+    pub seed: u64,
+}
+
+impl Default for Bootstrap {
+    fn default() -> Bootstrap {
+        Bootstrap {
+            resamples: 2000,
+            seed: 1,
+        }
+    }
+}
+
+/// Percentile bounds of a bootstrap confidence interval.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BootstrapInterval {
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Bootstrap {
+    /// Percentile 95% confidence interval of `measure`, resampling the given
+    /// trials with replacement within each emotion by face sex cell, so
+    /// trials pooled over cells keep every cell's size. Every call starts
+    /// again from the seed, so an interval does not depend on which other
+    /// columns were computed before it. Resamples the measure is undefined
+    /// for, such as a reaction time without correct responses, are left out.
+    pub fn confidence_interval_95(
+        &self,
+        trials: &[Trial],
+        measure: impl Fn(&[Trial]) -> Option<f64>,
+    ) -> Option<BootstrapInterval> {
+        if trials.is_empty() {
+            return None;
+        }
+        let cells = CELLS
+            .iter()
+            .map(|(condition, sex)| {
+                trials
+                    .iter()
+                    .filter(|trial| trial.condition == *condition && trial.sex == *sex)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|cell| !cell.is_empty())
+            .collect::<Vec<_>>();
+        let mut rng = Rng::new(self.seed);
+        let estimates = (0..self.resamples)
+            .filter_map(|_| {
+                let resample = cells
+                    .iter()
+                    .flat_map(|cell| {
+                        (0..cell.len())
+                            .map(|_| cell[rng.below(cell.len())].clone())
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                measure(&resample)
+            })
+            .collect::<Vec<_>>();
+        Some(BootstrapInterval {
+            lower: percentile(&estimates, 2.5)?,
+            upper: percentile(&estimates, 97.5)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bootstrap, BootstrapInterval, Rng};
+    use crate::{Condition, Sex, Trial};
+
+    fn trial(correct_response: bool) -> Trial {
+        Trial {
+            correct_response,
+            condition: Condition::Neutral,
+            sex: Sex::Male,
+            response_time_milliseconds: Some(600),
+            button_press_time_microseconds: Some(600_000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rng_reproducible_and_in_range() {
+        let mut first = Rng::new(42);
+        let mut second = Rng::new(42);
+        for _ in 0..100 {
+            let index = first.below(40);
+            assert_eq!(index, second.below(40));
+            assert!(index < 40);
+        }
+    }
+
+    #[test]
+    fn accuracy_confidence_interval() {
+        let trials = (0..40)
+            .map(|index| trial(index % 4 != 0))
+            .collect::<Vec<_>>();
+        let bootstrap = Bootstrap::default();
+        let measure = |trials: &[Trial]| Some(crate::accuracy_percentage(trials));
+        let interval = bootstrap.confidence_interval_95(&trials, measure).unwrap();
+        assert!(interval.lower < 75. && 75. < interval.upper);
+        assert!(interval.lower > 55. && interval.upper < 90.);
+        assert_eq!(
+            Some(interval),
+            bootstrap.confidence_interval_95(&trials, measure)
+        );
+    }
+
+    #[test]
+    fn constant_measure_and_empty_cell() {
+        let trials = vec![trial(true); 10];
+        let bootstrap = Bootstrap::default();
+        let measure = |trials: &[Trial]| {
+            crate::reaction_time_milliseconds(trials).map(|milliseconds| milliseconds as f64)
+        };
+        assert_eq!(
+            Some(BootstrapInterval {
+                lower: 600.,
+                upper: 600.
+            }),
+            bootstrap.confidence_interval_95(&trials, measure)
+        );
+        assert_eq!(None, bootstrap.confidence_interval_95(&[], measure));
+    }
+
+    #[test]
+    fn resamples_within_cells() {
+        // Every neutral male face answered, every happy female face missed.
+        let trials = (0..40)
+            .map(|index| Trial {
+                sex: if index < 20 { Sex::Male } else { Sex::Female },
+                condition: if index < 20 {
+                    Condition::Neutral
+                } else {
+                    Condition::Happy
+                },
+                ..trial(index < 20)
+            })
+            .collect::<Vec<_>>();
+        let measure = |trials: &[Trial]| Some(crate::accuracy_percentage(trials));
+        assert_eq!(
+            Some(BootstrapInterval {
+                lower: 50.,
+                upper: 50.
+            }),
+            Bootstrap::default().confidence_interval_95(&trials, measure)
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use crate::statistics::{mean, standard_deviation, ReactionTimeSummary};
+use crate::{accuracy_percentage, Trial};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Composite {
+    InverseEfficiency,
+    RateCorrect,
+    BalancedIntegration,
+}
+
+impl Composite {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Composite::InverseEfficiency => "IES (ms)",
+            Composite::RateCorrect => "RCS (correct/s)",
+            Composite::BalancedIntegration => "BIS",
+        }
+    }
+}
+
+impl std::str::FromStr for Composite {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ies" => Ok(Composite::InverseEfficiency),
+            "rcs" => Ok(Composite::RateCorrect),
+            "bis" => Ok(Composite::BalancedIntegration),
+            _ => Err(format!("unknown composite score: {}", s)),
+        }
+    }
+}
+
+fn proportion_correct(accuracy_trials: &[Trial]) -> f64 {
+    accuracy_percentage(accuracy_trials) / 100.
+}
+
+fn mean_reaction_time_milliseconds(reaction_time_trials: &[Trial]) -> Option<f64> {
+    ReactionTimeSummary::from_trials(reaction_time_trials).map(|summary| summary.mean)
+}
+
+/// Mean correct reaction time divided by the proportion of correct responses.
+pub fn inverse_efficiency_score(
+    accuracy_trials: &[Trial],
+    reaction_time_trials: &[Trial],
+) -> Option<f64> {
+    let proportion_correct = proportion_correct(accuracy_trials);
+    if proportion_correct > 0. {
+        mean_reaction_time_milliseconds(reaction_time_trials).map(|mean| mean / proportion_correct)
+    } else {
+        None
+    }
+}
+
+/// Correct responses per second spent responding, over every trial with a
+/// button press whether right or wrong.
+pub fn rate_correct_score(accuracy_trials: &[Trial]) -> Option<f64> {
+    let total_seconds = accuracy_trials
+        .iter()
+        .filter_map(|trial| {
+            trial
+                .button_press_time_microseconds
+                .map(|time| (time - trial.stimulus_time_microseconds) as f64 / 1_000_000.)
+        })
+        .sum::<f64>();
+    if total_seconds > 0. {
+        let correct = accuracy_trials
+            .iter()
+            .filter(|trial| trial.correct_response)
+            .count();
+        Some(correct as f64 / total_seconds)
+    } else {
+        None
+    }
+}
+
+/// The standardization behind the balanced integration score: proportion
+/// correct and mean reaction time are z-scored against every
+/// participant-by-cell observation being compared, then subtracted.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BalancedIntegration {
+    proportion_correct_mean: f64,
+    proportion_correct_standard_deviation: f64,
+    reaction_time_mean: f64,
+    reaction_time_standard_deviation: f64,
+}
+
+impl BalancedIntegration {
+    /// Each observation is a pair of accuracy trials and reaction time trials
+    /// for one participant's cell. Observations without a mean reaction time
+    /// are left out.
+    pub fn from_observations(observations: &[(Vec<Trial>, Vec<Trial>)]) -> Option<Self> {
+        let (proportions_correct, reaction_times): (Vec<f64>, Vec<f64>) = observations
+            .iter()
+            .filter_map(|(accuracy_trials, reaction_time_trials)| {
+                mean_reaction_time_milliseconds(reaction_time_trials)
+                    .map(|mean| (proportion_correct(accuracy_trials), mean))
+            })
+            .unzip();
+        Some(BalancedIntegration {
+            proportion_correct_mean: mean(&proportions_correct)?,
+            proportion_correct_standard_deviation: standard_deviation(&proportions_correct)?,
+            reaction_time_mean: mean(&reaction_times)?,
+            reaction_time_standard_deviation: standard_deviation(&reaction_times)?,
+        })
+    }
+
+    pub fn score(&self, accuracy_trials: &[Trial], reaction_time_trials: &[Trial]) -> Option<f64> {
+        if self.proportion_correct_standard_deviation == 0.
+            || self.reaction_time_standard_deviation == 0.
+        {
+            return None;
+        }
+        let mean = mean_reaction_time_milliseconds(reaction_time_trials)?;
+        Some(
+            (proportion_correct(accuracy_trials) - self.proportion_correct_mean)
+                / self.proportion_correct_standard_deviation
+                - (mean - self.reaction_time_mean) / self.reaction_time_standard_deviation,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BalancedIntegration;
+    use crate::{Condition, Sex, Trial};
+
+    fn trial(correct_response: bool, response_time_milliseconds: i64) -> Trial {
+        Trial {
+            correct_response,
+            condition: Condition::Neutral,
+            sex: Sex::Female,
+            response_time_milliseconds: if correct_response {
+                Some(response_time_milliseconds)
+            } else {
+                None
+            },
+            button_press_time_microseconds: Some(response_time_milliseconds * 1000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn inverse_efficiency_score() {
+        let trials = vec![
+            trial(true, 500),
+            trial(true, 700),
+            trial(false, 400),
+            trial(true, 600),
+        ];
+        assert_eq!(
+            Some(800.),
+            crate::composites::inverse_efficiency_score(&trials, &trials)
+        );
+        assert_eq!(
+            None,
+            crate::composites::inverse_efficiency_score(&[trial(false, 400)], &[])
+        );
+    }
+
+    #[test]
+    fn rate_correct_score() {
+        let trials = vec![
+            trial(true, 500),
+            trial(true, 700),
+            trial(false, 400),
+            trial(true, 400),
+        ];
+        assert_eq!(Some(1.5), crate::composites::rate_correct_score(&trials));
+    }
+
+    #[test]
+    fn balanced_integration_score() {
+        let fast_and_accurate = vec![trial(true, 400), trial(true, 400)];
+        let middling = vec![trial(true, 500), trial(false, 500)];
+        let slow_and_inaccurate = vec![trial(true, 600), trial(false, 600), trial(false, 600)];
+        let balanced_integration = BalancedIntegration::from_observations(&[
+            (fast_and_accurate.clone(), fast_and_accurate.clone()),
+            (middling.clone(), middling.clone()),
+            (slow_and_inaccurate.clone(), slow_and_inaccurate.clone()),
+        ])
+        .unwrap();
+        let scores = [&fast_and_accurate, &middling, &slow_and_inaccurate]
+            .into_iter()
+            .map(|trials| balanced_integration.score(trials, trials).unwrap())
+            .collect::<Vec<_>>();
+        assert!(scores[0] > scores[1] && scores[1] > scores[2]);
+        assert!(scores.iter().sum::<f64>().abs() < 1e-9);
+    }
+}
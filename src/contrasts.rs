@@ -0,0 +1,288 @@
+use crate::{trials_matching, Condition, Sex, Trial};
+
+/// One cell of a contrast and the weight it enters with. An unset condition or
+/// face sex pools over it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ContrastTerm {
+    pub weight: f64,
+    pub condition: Option<Condition>,
+    pub sex: Option<Sex>,
+}
+
+/// A weighted difference of cells, written like "Angry-Neutral" or
+/// "(Angry Male-Neutral Male)-(Angry Female-Neutral Female)". Cells are named
+/// by an emotion, a face sex, both, or "All".
+#[derive(Debug, PartialEq, Clone)]
+pub struct Contrast {
+    pub name: String,
+    pub terms: Vec<ContrastTerm>,
+}
+
+impl Contrast {
+    /// Applies `measure` to each cell's trials and combines the results. `None`
+    /// if any cell has no value.
+    pub fn evaluate(
+        &self,
+        trials: &[Trial],
+        measure: impl Fn(&[Trial]) -> Option<f64>,
+    ) -> Option<f64> {
+        self.terms
+            .iter()
+            .map(|term| {
+                measure(&trials_matching(
+                    trials,
+                    term.condition.as_ref(),
+                    term.sex.as_ref(),
+                ))
+                .map(|value| term.weight * value)
+            })
+            .sum()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Plus,
+    Minus,
+    Open,
+    Close,
+    Word(String),
+}
+
+fn tokenize(s: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for character in s.chars() {
+        let token = match character {
+            '+' => Some(Token::Plus),
+            '-' => Some(Token::Minus),
+            '(' => Some(Token::Open),
+            ')' => Some(Token::Close),
+            _ => None,
+        };
+        if token.is_some() || character.is_whitespace() {
+            if !word.is_empty() {
+                tokens.push(Token::Word(std::mem::take(&mut word)));
+            }
+        } else {
+            word.push(character);
+        }
+        tokens.extend(token);
+    }
+    if !word.is_empty() {
+        tokens.push(Token::Word(word));
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn expression(&mut self) -> Result<Vec<ContrastTerm>, String> {
+        let mut terms = self.signed()?;
+        loop {
+            let sign = match self.tokens.peek() {
+                Some(Token::Plus) => 1.,
+                Some(Token::Minus) => -1.,
+                _ => return Ok(terms),
+            };
+            self.tokens.next();
+            terms.extend(self.primary()?.into_iter().map(|mut term| {
+                term.weight *= sign;
+                term
+            }));
+        }
+    }
+
+    fn signed(&mut self) -> Result<Vec<ContrastTerm>, String> {
+        if self.tokens.peek() == Some(&Token::Minus) {
+            self.tokens.next();
+            Ok(self
+                .primary()?
+                .into_iter()
+                .map(|mut term| {
+                    term.weight = -term.weight;
+                    term
+                })
+                .collect::<Vec<_>>())
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Vec<ContrastTerm>, String> {
+        match self.tokens.next() {
+            Some(Token::Open) => {
+                let terms = self.expression()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(terms),
+                    _ => Err("missing closing parenthesis".to_string()),
+                }
+            }
+            Some(Token::Word(word)) => {
+                let mut term = ContrastTerm {
+                    weight: 1.,
+                    condition: None,
+                    sex: None,
+                };
+                self.cell_word(&mut term, &word)?;
+                while let Some(Token::Word(_)) = self.tokens.peek() {
+                    if let Some(Token::Word(word)) = self.tokens.next() {
+                        self.cell_word(&mut term, &word)?;
+                    }
+                }
+                Ok(vec![term])
+            }
+            _ => Err("expected a cell name".to_string()),
+        }
+    }
+
+    fn cell_word(&self, term: &mut ContrastTerm, word: &str) -> Result<(), String> {
+        let (condition, sex) = match word.to_lowercase().as_str() {
+            "angry" => (Some(Condition::Angry), None),
+            "happy" => (Some(Condition::Happy), None),
+            "neutral" => (Some(Condition::Neutral), None),
+            "male" => (None, Some(Sex::Male)),
+            "female" => (None, Some(Sex::Female)),
+            "all" => (None, None),
+            _ => return Err(format!("unknown cell name: {}", word)),
+        };
+        if (condition.is_some() && term.condition.is_some())
+            || (sex.is_some() && term.sex.is_some())
+        {
+            return Err(format!("conflicting cell name: {}", word));
+        }
+        term.condition = term.condition.take().or(condition);
+        term.sex = term.sex.take().or(sex);
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Contrast {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s).into_iter().peekable(),
+        };
+        let terms = parser.expression()?;
+        if let Some(token) = parser.tokens.next() {
+            return Err(format!("unexpected {:?} in contrast {}", token, s));
+        }
+        Ok(Contrast {
+            name: s.trim().to_string(),
+            terms,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Contrast, ContrastTerm};
+    use crate::statistics::ReactionTimeSummary;
+    use crate::{Condition, Sex, Trial};
+
+    fn term(weight: f64, condition: Option<Condition>, sex: Option<Sex>) -> ContrastTerm {
+        ContrastTerm {
+            weight,
+            condition,
+            sex,
+        }
+    }
+
+    fn trial(condition: Condition, sex: Sex, response_time_milliseconds: i64) -> Trial {
+        Trial {
+            correct_response: true,
+            condition,
+            sex,
+            response_time_milliseconds: Some(response_time_milliseconds),
+            button_press_time_microseconds: Some(response_time_milliseconds * 1000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_difference() {
+        assert_eq!(
+            Ok(Contrast {
+                name: "Angry-Neutral".to_string(),
+                terms: vec![
+                    term(1., Some(Condition::Angry), None),
+                    term(-1., Some(Condition::Neutral), None)
+                ]
+            }),
+            "Angry-Neutral".parse::<Contrast>()
+        );
+    }
+
+    #[test]
+    fn parse_interaction() {
+        assert_eq!(
+            Ok(vec![
+                term(1., Some(Condition::Angry), Some(Sex::Male)),
+                term(-1., Some(Condition::Neutral), Some(Sex::Male)),
+                term(-1., Some(Condition::Angry), Some(Sex::Female)),
+                term(1., Some(Condition::Neutral), Some(Sex::Female)),
+            ]),
+            "(Angry Male - Neutral Male) - (Angry Female - Neutral Female)"
+                .parse::<Contrast>()
+                .map(|contrast| contrast.terms)
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("Angry-Sad".parse::<Contrast>().is_err());
+        assert!("Angry Happy-Neutral".parse::<Contrast>().is_err());
+        assert!("(Angry-Neutral".parse::<Contrast>().is_err());
+        assert!("Angry-".parse::<Contrast>().is_err());
+    }
+
+    #[test]
+    fn evaluate() {
+        let trials = vec![
+            trial(Condition::Angry, Sex::Male, 700),
+            trial(Condition::Angry, Sex::Female, 650),
+            trial(Condition::Neutral, Sex::Male, 600),
+            trial(Condition::Neutral, Sex::Female, 500),
+        ];
+        let reaction_time =
+            |trials: &[Trial]| ReactionTimeSummary::from_trials(trials).map(|summary| summary.mean);
+        assert_eq!(
+            Some(125.),
+            "Angry-Neutral"
+                .parse::<Contrast>()
+                .unwrap()
+                .evaluate(&trials, reaction_time)
+        );
+        assert_eq!(
+            Some(-50.),
+            "(Angry Male-Neutral Male)-(Angry Female-Neutral Female)"
+                .parse::<Contrast>()
+                .unwrap()
+                .evaluate(&trials, reaction_time)
+        );
+        // Not rounded to whole milliseconds per cell first.
+        let trials = vec![
+            trial(Condition::Angry, Sex::Male, 700),
+            trial(Condition::Angry, Sex::Male, 701),
+            trial(Condition::Neutral, Sex::Male, 600),
+        ];
+        assert_eq!(
+            Some(100.5),
+            "Angry-Neutral"
+                .parse::<Contrast>()
+                .unwrap()
+                .evaluate(&trials, reaction_time)
+        );
+        assert_eq!(
+            None,
+            "Happy-Neutral"
+                .parse::<Contrast>()
+                .unwrap()
+                .evaluate(&trials, reaction_time)
+        );
+    }
+}
@@ -0,0 +1,165 @@
+/// A participant table read from CSV, one row per participant with the
+/// subject ID in the first column.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Demographics {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+fn split_csv_line(line: &str) -> Vec<String> {
+    line.split(',')
+        .map(|field| field.trim().trim_matches('"').to_string())
+        .collect::<Vec<_>>()
+}
+
+pub fn parse_demographics(input: &str) -> Demographics {
+    let mut lines = input.lines().filter(|line| !line.trim().is_empty());
+    let columns = lines.next().map(split_csv_line).unwrap_or_default();
+    Demographics {
+        columns,
+        rows: lines.map(split_csv_line).collect::<Vec<_>>(),
+    }
+}
+
+impl Demographics {
+    pub fn row(&self, subject: &str) -> Option<&Vec<String>> {
+        self.rows
+            .iter()
+            .find(|row| row.first().is_some_and(|id| id == subject))
+    }
+
+    pub fn value(&self, subject: &str, column: &str) -> Option<&str> {
+        let index = self.columns.iter().position(|name| name == column)?;
+        self.row(subject)?.get(index).map(|value| value.as_str())
+    }
+
+    /// Every column but the subject ID, as joined onto the outputs.
+    pub fn covariate_columns(&self) -> &[String] {
+        self.columns.get(1..).unwrap_or_default()
+    }
+
+    /// A participant's values for `covariate_columns`, "NA" where the
+    /// participant or the value is missing.
+    pub fn covariates(&self, subject: &str) -> Vec<String> {
+        (1..self.columns.len())
+            .map(|index| {
+                self.row(subject)
+                    .and_then(|row| row.get(index))
+                    .filter(|value| !value.is_empty())
+                    .map_or("NA".to_string(), |value| value.clone())
+            })
+            .collect::<Vec<_>>()
+    }
+
+    /// Subject IDs listed here without a recording among `subjects`.
+    pub fn unmatched_subjects(&self, subjects: &[String]) -> Vec<&str> {
+        self.rows
+            .iter()
+            .filter_map(|row| row.first())
+            .filter(|id| !subjects.contains(id))
+            .map(|id| id.as_str())
+            .collect::<Vec<_>>()
+    }
+}
+
+/// Swaps the response buttons for participants whose value in a demographics
+/// column matches, such as left-handers or one counterbalancing version.
+/// Written `<column>=<value>`; the value is matched ignoring case.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SwapRule {
+    pub column: String,
+    pub value: String,
+}
+
+impl SwapRule {
+    pub fn swaps(&self, demographics: &Demographics, subject: &str) -> bool {
+        demographics
+            .value(subject, &self.column)
+            .is_some_and(|value| value.eq_ignore_ascii_case(&self.value))
+    }
+}
+
+impl std::str::FromStr for SwapRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((column, value)) if !column.trim().is_empty() => Ok(SwapRule {
+                column: column.trim().to_string(),
+                value: value.trim().to_string(),
+            }),
+            _ => Err(format!("expected <column>=<value>: {}", s)),
+        }
+    }
+}
+
+/// The subject ID a recording is matched on: its file name without the
+/// extension.
+pub fn subject_id(file_path: &str) -> String {
+    std::path::Path::new(file_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse_demographics() {
+        let demographics = crate::demographics::parse_demographics(
+            "Subject, Age, Group
+p01, 9, control
+
+\"p02\", 11, patient
+",
+        );
+        assert_eq!(vec!["Subject", "Age", "Group"], demographics.columns);
+        assert_eq!(Some("patient"), demographics.value("p02", "Group"));
+        assert_eq!(Some("9"), demographics.value("p01", "Age"));
+        assert_eq!(None, demographics.value("p03", "Age"));
+        assert_eq!(None, demographics.value("p01", "Handedness"));
+    }
+
+    #[test]
+    fn covariates_and_unmatched_subjects() {
+        let demographics = crate::demographics::parse_demographics(
+            "Subject, Age, Handedness
+p01, 9, right
+p02, , left
+p04, 10, right
+",
+        );
+        assert_eq!(vec!["Age", "Handedness"], demographics.covariate_columns());
+        assert_eq!(vec!["NA", "left"], demographics.covariates("p02"));
+        assert_eq!(vec!["NA", "NA"], demographics.covariates("p03"));
+        assert_eq!(
+            vec!["p04"],
+            demographics.unmatched_subjects(&["p01".to_string(), "p02".to_string()])
+        );
+    }
+
+    #[test]
+    fn swap_rule() {
+        let demographics = crate::demographics::parse_demographics(
+            "Subject, Handedness
+p01, right
+p02, Left
+",
+        );
+        let rule = "Handedness=left"
+            .parse::<crate::demographics::SwapRule>()
+            .unwrap();
+        assert!(!rule.swaps(&demographics, "p01"));
+        assert!(rule.swaps(&demographics, "p02"));
+        assert!(!rule.swaps(&demographics, "p03"));
+        assert!("left".parse::<crate::demographics::SwapRule>().is_err());
+    }
+
+    #[test]
+    fn subject_id() {
+        assert_eq!(
+            "p01",
+            crate::demographics::subject_id("/data/faces/p01.evt")
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::statistics::ReactionTimeSummary;
+use crate::Trial;
+
+/// Scaling parameter of the diffusion process, 0.1 by convention.
+const DIFFUSION_SCALING: f64 = 0.1;
+
+/// How the proportion correct was adjusted before fitting. The EZ equations
+/// are undefined at 100% accuracy, so, following Wagenmakers et al. (2007),
+/// it is replaced by 1 - 1/(2n), where n is the number of trials. At 0% there
+/// are no correct reaction times to fit in the first place, so the cell is
+/// reported as `EzUndefined::NoneCorrect` instead.
+#[derive(Debug, PartialEq, Clone)]
+pub enum EdgeCorrection {
+    None,
+    AllCorrect,
+}
+
+impl EdgeCorrection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EdgeCorrection::None => "none",
+            EdgeCorrection::AllCorrect => "100% -> 1-1/(2n)",
+        }
+    }
+}
+
+/// Why a cell has no EZ-diffusion estimates.
+#[derive(Debug, PartialEq, Clone)]
+pub enum EzUndefined {
+    NoTrials,
+    /// 0% accuracy leaves no correct reaction times.
+    NoneCorrect,
+    TooFewReactionTimes,
+    /// Every correct reaction time the same, so no variance to fit.
+    ConstantReactionTimes,
+    /// 50% accuracy has no drift to estimate.
+    Chance,
+}
+
+impl EzUndefined {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EzUndefined::NoTrials => "no trials",
+            EzUndefined::NoneCorrect => "0% correct",
+            EzUndefined::TooFewReactionTimes => "fewer than 2 correct reaction times",
+            EzUndefined::ConstantReactionTimes => "no reaction time variance",
+            EzUndefined::Chance => "50% correct",
+        }
+    }
+}
+
+/// EZ-diffusion (Wagenmakers, van der Maas & Grasman, 2007) estimates, with
+/// reaction times in seconds as in the original equations.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EzDiffusion {
+    pub drift_rate: f64,
+    pub boundary_separation: f64,
+    pub non_decision_time_milliseconds: f64,
+    pub edge_correction: EdgeCorrection,
+}
+
+/// The closed-form equations, from the proportion correct and the variance
+/// and mean of correct reaction times in seconds. Accuracy of exactly 50%
+/// has no drift to estimate, so gives `None`.
+pub fn ez_parameters(
+    proportion_correct: f64,
+    variance_seconds: f64,
+    mean_seconds: f64,
+) -> Option<(f64, f64, f64)> {
+    if proportion_correct == 0.5 || variance_seconds <= 0. {
+        return None;
+    }
+    let logit = (proportion_correct / (1. - proportion_correct)).ln();
+    let x = logit
+        * (logit * proportion_correct.powi(2) - logit * proportion_correct + proportion_correct
+            - 0.5)
+        / variance_seconds;
+    let drift_rate = (proportion_correct - 0.5).signum() * DIFFUSION_SCALING * x.powf(0.25);
+    let boundary_separation = DIFFUSION_SCALING.powi(2) * logit / drift_rate;
+    let y = -drift_rate * boundary_separation / DIFFUSION_SCALING.powi(2);
+    let mean_decision_time =
+        boundary_separation / (2. * drift_rate) * (1. - y.exp()) / (1. + y.exp());
+    Some((
+        drift_rate,
+        boundary_separation,
+        mean_seconds - mean_decision_time,
+    ))
+}
+
+/// Accuracy comes from `accuracy_trials`, and the reaction time mean and
+/// variance from the correct responses among `reaction_time_trials`.
+pub fn ez_diffusion(
+    accuracy_trials: &[Trial],
+    reaction_time_trials: &[Trial],
+) -> Result<EzDiffusion, EzUndefined> {
+    if accuracy_trials.is_empty() {
+        return Err(EzUndefined::NoTrials);
+    }
+    let count = accuracy_trials.len() as f64;
+    let proportion_correct = crate::accuracy_percentage(accuracy_trials) / 100.;
+    let (proportion_correct, edge_correction) = match proportion_correct {
+        0. => return Err(EzUndefined::NoneCorrect),
+        0.5 => return Err(EzUndefined::Chance),
+        1. => (1. - 1. / (2. * count), EdgeCorrection::AllCorrect),
+        _ => (proportion_correct, EdgeCorrection::None),
+    };
+    let summary = ReactionTimeSummary::from_trials(reaction_time_trials)
+        .ok_or(EzUndefined::TooFewReactionTimes)?;
+    let mean_seconds = summary.mean / 1000.;
+    let variance_seconds = (summary
+        .standard_deviation
+        .ok_or(EzUndefined::TooFewReactionTimes)?
+        / 1000.)
+        .powi(2);
+    let (drift_rate, boundary_separation, non_decision_time) =
+        ez_parameters(proportion_correct, variance_seconds, mean_seconds)
+            .ok_or(EzUndefined::ConstantReactionTimes)?;
+    Ok(EzDiffusion {
+        drift_rate,
+        boundary_separation,
+        non_decision_time_milliseconds: non_decision_time * 1000.,
+        edge_correction,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EdgeCorrection, EzUndefined};
+    use crate::{Condition, Sex, Trial};
+
+    fn round(value: f64) -> f64 {
+        (value * 1000.).round() / 1000.
+    }
+
+    fn trial(correct_response: bool, response_time_milliseconds: i64) -> Trial {
+        Trial {
+            correct_response,
+            condition: Condition::Angry,
+            sex: Sex::Female,
+            response_time_milliseconds: if correct_response {
+                Some(response_time_milliseconds)
+            } else {
+                None
+            },
+            button_press_time_microseconds: Some(response_time_milliseconds * 1000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn wagenmakers_example() {
+        // The worked example from the original paper: v = 0.1, a = 0.14,
+        // Ter = 0.3.
+        let (drift_rate, boundary_separation, non_decision_time) =
+            super::ez_parameters(0.802, 0.112, 0.723).unwrap();
+        assert_eq!(0.1, round(drift_rate));
+        assert_eq!(0.14, round(boundary_separation));
+        assert_eq!(0.3, round(non_decision_time));
+    }
+
+    #[test]
+    fn below_chance_and_at_chance() {
+        let (drift_rate, boundary_separation, _) =
+            super::ez_parameters(0.198, 0.112, 0.723).unwrap();
+        assert!(drift_rate < 0.);
+        assert!(boundary_separation > 0.);
+        assert_eq!(None, super::ez_parameters(0.5, 0.112, 0.723));
+    }
+
+    #[test]
+    fn all_correct_corrected() {
+        let trials = [500, 550, 600, 650, 700]
+            .into_iter()
+            .map(|response_time| trial(true, response_time))
+            .collect::<Vec<_>>();
+        let diffusion = super::ez_diffusion(&trials, &trials).unwrap();
+        assert_eq!(EdgeCorrection::AllCorrect, diffusion.edge_correction);
+        assert!(diffusion.drift_rate > 0.);
+        assert!(diffusion.non_decision_time_milliseconds < 600.);
+    }
+
+    #[test]
+    fn undefined_cells_say_why() {
+        let trials = vec![trial(false, 500), trial(false, 600)];
+        assert_eq!(
+            Err(EzUndefined::NoneCorrect),
+            super::ez_diffusion(&trials, &trials)
+        );
+        let trials = vec![trial(true, 500), trial(false, 600)];
+        assert_eq!(
+            Err(EzUndefined::Chance),
+            super::ez_diffusion(&trials, &trials)
+        );
+        let trials = vec![trial(true, 500), trial(true, 500), trial(false, 600)];
+        assert_eq!(
+            Err(EzUndefined::ConstantReactionTimes),
+            super::ez_diffusion(&trials, &trials)
+        );
+        assert_eq!(Err(EzUndefined::NoTrials), super::ez_diffusion(&[], &[]));
+    }
+}
@@ -0,0 +1,201 @@
+use crate::statistics::{correct_reaction_times, ln_normal_cdf, mean, standard_deviation};
+use crate::Trial;
+
+const MAXIMUM_ITERATIONS: usize = 5000;
+// The fit has converged once the log-likelihoods at the simplex vertices
+// agree to within this.
+const TOLERANCE: f64 = 1e-9;
+// Fewer correct responses than this say too little about the tail to fit.
+const MINIMUM_COUNT: usize = 10;
+
+/// Maximum-likelihood ex-Gaussian fit of correct-response reaction times.
+/// `mu` and `sigma` describe the Gaussian component and `tau` the
+/// exponential tail, all in milliseconds.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExGaussian {
+    pub mu: f64,
+    pub sigma: f64,
+    pub tau: f64,
+    pub log_likelihood: f64,
+    pub iterations: usize,
+    /// Whether the simplex settled within `MAXIMUM_ITERATIONS`. Estimates
+    /// from a fit that did not converge should not be trusted.
+    pub converged: bool,
+}
+
+/// Natural log of the ex-Gaussian probability density at `x`.
+pub fn ln_density(x: f64, mu: f64, sigma: f64, tau: f64) -> f64 {
+    -tau.ln()
+        + (mu - x) / tau
+        + sigma * sigma / (2. * tau * tau)
+        + ln_normal_cdf((x - mu) / sigma - sigma / tau)
+}
+
+fn log_likelihood(reaction_times: &[f64], mu: f64, sigma: f64, tau: f64) -> f64 {
+    reaction_times
+        .iter()
+        .map(|&x| ln_density(x, mu, sigma, tau))
+        .sum::<f64>()
+}
+
+/// Method of moments estimates, used as the starting point. The skewness is
+/// clamped so the tail takes between 10% and 90% of the spread.
+fn starting_point(reaction_times: &[f64]) -> Option<[f64; 3]> {
+    let mean = mean(reaction_times)?;
+    let standard_deviation = standard_deviation(reaction_times)?;
+    if standard_deviation <= 0. {
+        return None;
+    }
+    let skewness = reaction_times
+        .iter()
+        .map(|x| ((x - mean) / standard_deviation).powi(3))
+        .sum::<f64>()
+        / reaction_times.len() as f64;
+    let tail_share = (skewness / 2.).cbrt().clamp(0.1, 0.9);
+    let tau = standard_deviation * tail_share;
+    let sigma = standard_deviation * (1. - tail_share * tail_share).sqrt();
+    Some([mean - tau, sigma, tau])
+}
+
+/// Nelder-Mead on mu, ln sigma and ln tau, so the scale parameters stay
+/// positive. `None` with fewer than `MINIMUM_COUNT` correct responses or no
+/// spread in their reaction times.
+pub fn fit_ex_gaussian(trials: &[Trial]) -> Option<ExGaussian> {
+    let reaction_times = correct_reaction_times(trials);
+    if reaction_times.len() < MINIMUM_COUNT {
+        return None;
+    }
+    let [mu, sigma, tau] = starting_point(&reaction_times)?;
+    let objective = |point: &[f64; 3]| {
+        let value = -log_likelihood(&reaction_times, point[0], point[1].exp(), point[2].exp());
+        if value.is_nan() {
+            f64::INFINITY
+        } else {
+            value
+        }
+    };
+    let start = [mu, sigma.ln(), tau.ln()];
+    let steps = [sigma.max(1.) * 0.5, 0.2, 0.2];
+    let mut simplex = (0..4)
+        .map(|vertex| {
+            let mut point = start;
+            if vertex > 0 {
+                point[vertex - 1] += steps[vertex - 1];
+            }
+            (objective(&point), point)
+        })
+        .collect::<Vec<_>>();
+    let mut iterations = 0;
+    let mut converged = false;
+    while iterations < MAXIMUM_ITERATIONS {
+        simplex.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if (simplex[3].0 - simplex[0].0).abs() <= TOLERANCE * (1. + simplex[0].0.abs()) {
+            converged = true;
+            break;
+        }
+        iterations += 1;
+        let mut centroid = [0.; 3];
+        for (_, point) in &simplex[..3] {
+            for (sum, coordinate) in centroid.iter_mut().zip(point) {
+                *sum += coordinate / 3.;
+            }
+        }
+        let along = |coefficient: f64| {
+            let mut point = centroid;
+            for (coordinate, worst) in point.iter_mut().zip(&simplex[3].1) {
+                *coordinate += coefficient * (*coordinate - worst);
+            }
+            (objective(&point), point)
+        };
+        let reflected = along(1.);
+        if reflected.0 < simplex[0].0 {
+            let expanded = along(2.);
+            simplex[3] = if expanded.0 < reflected.0 {
+                expanded
+            } else {
+                reflected
+            };
+        } else if reflected.0 < simplex[2].0 {
+            simplex[3] = reflected;
+        } else {
+            let contracted = if reflected.0 < simplex[3].0 {
+                along(0.5)
+            } else {
+                along(-0.5)
+            };
+            if contracted.0 < simplex[3].0.min(reflected.0) {
+                simplex[3] = contracted;
+            } else {
+                let best = simplex[0].1;
+                for vertex in simplex.iter_mut().skip(1) {
+                    for (coordinate, best) in vertex.1.iter_mut().zip(&best) {
+                        *coordinate = best + 0.5 * (*coordinate - best);
+                    }
+                    vertex.0 = objective(&vertex.1);
+                }
+            }
+        }
+    }
+    simplex.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (negative_log_likelihood, [mu, ln_sigma, ln_tau]) = simplex[0];
+    Some(ExGaussian {
+        mu,
+        sigma: ln_sigma.exp(),
+        tau: ln_tau.exp(),
+        log_likelihood: -negative_log_likelihood,
+        iterations,
+        converged,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bootstrap::Rng;
+    use crate::{Condition, Sex, Trial};
+
+    fn trial(response_time_microseconds: i64) -> Trial {
+        Trial {
+            correct_response: true,
+            condition: Condition::Happy,
+            sex: Sex::Female,
+            response_time_milliseconds: Some((response_time_microseconds + 500) / 1000),
+            button_press_time_microseconds: Some(response_time_microseconds),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ln_density() {
+        // mu = 0, sigma = 1, tau = 1 at x = 0: exp(1/2) * Phi(-1).
+        assert_eq!(
+            0.261578,
+            ((super::ln_density(0., 0., 1., 1.).exp()) * 1e6).round() / 1e6
+        );
+    }
+
+    #[test]
+    fn recovers_simulated_parameters() {
+        let mut rng = Rng::new(7);
+        let trials = (0..2000)
+            .map(|_| {
+                let normal = (-2. * rng.uniform().ln()).sqrt()
+                    * (2. * std::f64::consts::PI * rng.uniform()).cos();
+                let exponential = -rng.uniform().ln();
+                trial(((400. + 40. * normal + 100. * exponential) * 1000.) as i64)
+            })
+            .collect::<Vec<_>>();
+        let fit = super::fit_ex_gaussian(&trials).unwrap();
+        assert!(fit.converged);
+        assert!((fit.mu - 400.).abs() < 10., "mu {}", fit.mu);
+        assert!((fit.sigma - 40.).abs() < 8., "sigma {}", fit.sigma);
+        assert!((fit.tau - 100.).abs() < 10., "tau {}", fit.tau);
+    }
+
+    #[test]
+    fn too_few_responses() {
+        let trials = (0..9)
+            .map(|index| trial(500_000 + index * 10_000))
+            .collect::<Vec<_>>();
+        assert_eq!(None, super::fit_ex_gaussian(&trials));
+    }
+}
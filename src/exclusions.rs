@@ -0,0 +1,216 @@
+use crate::response_window::{ResponseTiming, ResponseWindow};
+use crate::trimming::Trimming;
+use crate::Trial;
+
+/// Which trials are left out of the accuracy and reaction time summaries.
+#[derive(Debug, Default, Clone)]
+pub struct Exclusions {
+    pub artifacts: bool,
+    pub response_window: ResponseWindow,
+    /// Score anticipatory and late responses as errors instead of leaving
+    /// them in accuracy as they were pressed.
+    pub out_of_window_as_errors: bool,
+    pub trimming: Trimming,
+    /// Leave presses of both buttons at once out of accuracy, rather than
+    /// counting them as errors.
+    pub both_buttons: bool,
+}
+
+impl Exclusions {
+    pub fn excludes(&self, trial: &Trial) -> bool {
+        (self.artifacts && trial.artifact) || (self.both_buttons && trial.both_buttons)
+    }
+
+    /// The trials `accuracy_percentage` should be computed over.
+    pub fn accuracy_trials(&self, trials: &[Trial]) -> Vec<Trial> {
+        self.included(trials)
+            .into_iter()
+            .map(|mut trial| {
+                if self.out_of_window_as_errors
+                    && self.response_window.timing(&trial) != ResponseTiming::InWindow
+                {
+                    trial.correct_response = false;
+                    trial.response_time_milliseconds = None;
+                }
+                trial
+            })
+            .collect::<Vec<_>>()
+    }
+
+    /// The trials `reaction_time_milliseconds` should be computed over.
+    /// Trimming is within each cell of the trials passed in, so pass one
+    /// participant's trials at a time.
+    pub fn reaction_time_trials(&self, trials: &[Trial]) -> Vec<Trial> {
+        self.trimming.trim(&self.in_window(trials))
+    }
+
+    pub fn trimmed_count(&self, trials: &[Trial]) -> usize {
+        self.trimming.removed_count(&self.in_window(trials))
+    }
+
+    pub fn excluded_count(&self, trials: &[Trial]) -> usize {
+        trials.iter().filter(|trial| self.excludes(trial)).count()
+    }
+
+    fn in_window(&self, trials: &[Trial]) -> Vec<Trial> {
+        self.included(trials)
+            .into_iter()
+            .filter(|trial| self.response_window.timing(trial) == ResponseTiming::InWindow)
+            .collect::<Vec<_>>()
+    }
+
+    fn included(&self, trials: &[Trial]) -> Vec<Trial> {
+        trials
+            .iter()
+            .filter(|trial| !self.excludes(trial))
+            .cloned()
+            .collect::<Vec<_>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Exclusions;
+    use crate::response_window::ResponseWindow;
+    use crate::trimming::Trimming;
+    use crate::{Condition, Sex, Trial};
+
+    fn trial(correct_response: bool, artifact: bool) -> Trial {
+        Trial {
+            correct_response,
+            condition: Condition::Happy,
+            sex: Sex::Female,
+            response_time_milliseconds: if correct_response { Some(500) } else { None },
+            button_press_time_microseconds: Some(500_000),
+            artifact,
+            ..Default::default()
+        }
+    }
+
+    fn trial_with_response_time(response_time_milliseconds: i64) -> Trial {
+        Trial {
+            correct_response: true,
+            condition: Condition::Happy,
+            sex: Sex::Female,
+            response_time_milliseconds: Some(response_time_milliseconds),
+            button_press_time_microseconds: Some(response_time_milliseconds * 1000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn artifacts_kept_by_default() {
+        let trials = vec![trial(true, true), trial(false, false)];
+        let exclusions = Exclusions::default();
+        assert_eq!(
+            50.,
+            crate::accuracy_percentage(&exclusions.accuracy_trials(&trials))
+        );
+        assert_eq!(0, exclusions.excluded_count(&trials));
+    }
+
+    #[test]
+    fn artifacts_excluded() {
+        let trials = vec![trial(true, true), trial(false, false), trial(true, false)];
+        let exclusions = Exclusions {
+            artifacts: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            50.,
+            crate::accuracy_percentage(&exclusions.accuracy_trials(&trials))
+        );
+        assert_eq!(2, exclusions.reaction_time_trials(&trials).len());
+        assert_eq!(1, exclusions.excluded_count(&trials));
+    }
+
+    #[test]
+    fn both_buttons_excluded() {
+        let both_buttons = Trial {
+            both_buttons: true,
+            ..trial(false, false)
+        };
+        let trials = vec![both_buttons, trial(true, false), trial(false, false)];
+        assert_eq!(
+            100. / 3.,
+            crate::accuracy_percentage(&Exclusions::default().accuracy_trials(&trials))
+        );
+        let exclusions = Exclusions {
+            both_buttons: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            50.,
+            crate::accuracy_percentage(&exclusions.accuracy_trials(&trials))
+        );
+        assert_eq!(1, exclusions.excluded_count(&trials));
+    }
+
+    #[test]
+    fn out_of_window_responses_left_out_of_reaction_time() {
+        let trials = vec![
+            trial_with_response_time(80),
+            trial_with_response_time(500),
+            trial_with_response_time(700),
+            trial_with_response_time(4000),
+        ];
+        let exclusions = Exclusions {
+            response_window: ResponseWindow {
+                minimum_milliseconds: Some(150),
+                maximum_milliseconds: Some(3000),
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(600),
+            crate::reaction_time_milliseconds(&exclusions.reaction_time_trials(&trials))
+        );
+        assert_eq!(
+            100.,
+            crate::accuracy_percentage(&exclusions.accuracy_trials(&trials))
+        );
+    }
+
+    #[test]
+    fn trimming_after_response_window() {
+        let trials = [500, 520, 540, 560, 580, 600, 620, 640, 660, 3000, 9000]
+            .into_iter()
+            .map(trial_with_response_time)
+            .collect::<Vec<_>>();
+        let exclusions = Exclusions {
+            response_window: ResponseWindow {
+                minimum_milliseconds: None,
+                maximum_milliseconds: Some(5000),
+            },
+            trimming: Trimming::StandardDeviations(2.5),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(580),
+            crate::reaction_time_milliseconds(&exclusions.reaction_time_trials(&trials))
+        );
+        assert_eq!(1, exclusions.trimmed_count(&trials));
+    }
+
+    #[test]
+    fn out_of_window_responses_as_errors() {
+        let trials = vec![
+            trial_with_response_time(80),
+            trial_with_response_time(500),
+            trial_with_response_time(700),
+            trial_with_response_time(4000),
+        ];
+        let exclusions = Exclusions {
+            response_window: ResponseWindow {
+                minimum_milliseconds: Some(150),
+                maximum_milliseconds: Some(3000),
+            },
+            out_of_window_as_errors: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            50.,
+            crate::accuracy_percentage(&exclusions.accuracy_trials(&trials))
+        );
+    }
+}
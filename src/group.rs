@@ -0,0 +1,65 @@
+use crate::statistics::{mean, standard_deviation, student_t_quantile};
+
+/// Summary of one measure across participants, with a t-based 95%
+/// confidence interval for the mean.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GroupDescriptives {
+    pub count: usize,
+    pub mean: f64,
+    pub standard_deviation: Option<f64>,
+    pub standard_error: Option<f64>,
+    pub confidence_interval_95: Option<(f64, f64)>,
+}
+
+/// Non-finite values, such as a participant's NaN reaction time in a cell
+/// without correct responses, are left out.
+pub fn describe(values: &[f64]) -> Option<GroupDescriptives> {
+    let values = values
+        .iter()
+        .copied()
+        .filter(|value| value.is_finite())
+        .collect::<Vec<_>>();
+    let mean = mean(&values)?;
+    let standard_deviation = standard_deviation(&values);
+    let standard_error = standard_deviation.map(|sd| sd / (values.len() as f64).sqrt());
+    let confidence_interval_95 = standard_error.map(|standard_error| {
+        let margin = student_t_quantile(0.975, (values.len() - 1) as f64) * standard_error;
+        (mean - margin, mean + margin)
+    });
+    Some(GroupDescriptives {
+        count: values.len(),
+        mean,
+        standard_deviation,
+        standard_error,
+        confidence_interval_95,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    fn round(value: f64) -> f64 {
+        (value * 1e4).round() / 1e4
+    }
+
+    #[test]
+    fn describe() {
+        let descriptives = crate::group::describe(&[80., 90., f64::NAN, 85., 95., 75.]).unwrap();
+        assert_eq!(5, descriptives.count);
+        assert_eq!(85., descriptives.mean);
+        assert_eq!(Some(7.9057), descriptives.standard_deviation.map(round));
+        assert_eq!(Some(3.5355), descriptives.standard_error.map(round));
+        assert_eq!(
+            Some((75.1838, 94.8162)),
+            descriptives
+                .confidence_interval_95
+                .map(|(lower, upper)| (round(lower), round(upper)))
+        );
+    }
+
+    #[test]
+    fn describe_single_participant() {
+        let descriptives = crate::group::describe(&[80.]).unwrap();
+        assert_eq!(None, descriptives.confidence_interval_95);
+        assert_eq!(None, crate::group::describe(&[f64::NAN]));
+    }
+}
@@ -1,3 +1,34 @@
+pub mod anova;
+pub mod artifacts;
+pub mod bootstrap;
+pub mod composites;
+pub mod contrasts;
+pub mod demographics;
+pub mod diffusion;
+pub mod ex_gaussian;
+pub mod exclusions;
+pub mod group;
+pub mod recording;
+pub mod response_mapping;
+pub mod response_policy;
+pub mod response_window;
+pub mod sequential;
+pub mod signal_detection;
+pub mod simulation;
+pub mod statistics;
+pub mod time_on_task;
+pub mod timing;
+pub mod triggers;
+pub mod trimming;
+pub mod validation;
+
+use triggers::{Button, TriggerDecoder};
+
+// The condition trigger and the visual onset of one trial arrive closer
+// together than this.
+const TRIAL_START_WINDOW_MICROSECONDS: i64 = 100_000;
+pub(crate) const CONDITION_CODES: [i32; 6] = [21, 22, 23, 31, 32, 33];
+
 #[derive(Debug, PartialEq)]
 pub struct Event {
     time_microseconds: i64,
@@ -37,63 +68,220 @@ pub struct Trial {
     pub condition: Condition,
     pub sex: Sex,
     pub response_time_milliseconds: Option<i64>,
+    pub stimulus_time_microseconds: i64,
+    pub button_press_time_microseconds: Option<i64>,
+    pub artifact: bool,
+    /// Counts up from 1 after every break of more than 10 s between trials.
+    pub block: usize,
+    /// What `stimulus_time_microseconds` was taken from.
+    pub onset_reference: OnsetReference,
+    /// Button presses after the first one.
+    pub extra_presses: usize,
+    /// The scored press had both buttons down at once (768), so it is an
+    /// error but neither a male nor a female answer.
+    pub both_buttons: bool,
+    /// The button of the scored press, `None` without a press or with both
+    /// or a garbled code.
+    pub response_button: Option<Button>,
+    /// The button that answers the face sex under the response mapping,
+    /// `None` for an unrecognised condition code.
+    pub correct_button: Option<Button>,
+}
+
+/// The event reaction times are measured from.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OnsetReference {
+    /// The visual onset bit (12), set by the photodiode.
+    Photodiode,
+    /// The condition trigger plus the display lag, for trials without a
+    /// visual onset.
+    ConditionTrigger,
+}
+
+impl std::fmt::Display for OnsetReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OnsetReference::Photodiode => write!(f, "photodiode"),
+            OnsetReference::ConditionTrigger => write!(f, "condition trigger"),
+        }
+    }
+}
+
+impl Trial {
+    /// Time from stimulus onset to the button press, whether or not the
+    /// press was correct.
+    pub fn button_press_latency_milliseconds(&self) -> Option<i64> {
+        self.button_press_time_microseconds
+            .map(|time| (time - self.stimulus_time_microseconds + 500) / 1000)
+    }
 }
 
-fn has_bit_set(x: i32, n: i32) -> bool {
-    let mask = 1 << n;
-    (x | mask) == x
+/// An unanswered happy male trial at time 0, as an unrecognised trial is
+/// reconstructed, for tests to fill in the fields they need.
+#[cfg(test)]
+impl Default for Trial {
+    fn default() -> Self {
+        Trial {
+            correct_response: false,
+            condition: Condition::Happy,
+            sex: Sex::Male,
+            response_time_milliseconds: None,
+            stimulus_time_microseconds: 0,
+            button_press_time_microseconds: None,
+            artifact: false,
+            block: 1,
+            onset_reference: OnsetReference::Photodiode,
+            extra_presses: 0,
+            both_buttons: false,
+            response_button: None,
+            correct_button: None,
+        }
+    }
 }
 
-fn trial_from_response_ready_index(events: &[Event]) -> Trial {
-    let response = events
+/// Which button is correct for which face sex. The standard mapping has
+/// button 1 (256) for male faces and button 2 (512) for female faces.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum ResponseMapping {
+    #[default]
+    Standard,
+    Swapped,
+}
+
+/// Which press scores a trial with more than one.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum ResponsePolicy {
+    #[default]
+    First,
+    Last,
+    /// Scored on the first press, but incorrect if any press was wrong.
+    AnyWrong,
+}
+
+/// How events are turned into trials.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ReconstructionOptions {
+    pub mapping: ResponseMapping,
+    pub response_policy: ResponsePolicy,
+    /// Added to the condition trigger time when a trial has no visual onset
+    /// to measure reaction time from.
+    pub display_lag_microseconds: i64,
+    pub decoder: TriggerDecoder,
+}
+
+/// The condition trigger and the visual onset among the first two events of
+/// a trial. Both can be the same event, when the condition code arrives
+/// combined with the photodiode. A visual onset only counts within the
+/// trial start window of the condition trigger, so a lone one before a break
+/// is not taken for the stimulus after it. Of two, such as a repeated
+/// trigger, the later one is taken.
+pub fn onset_events<'a>(
+    events: &'a [Event],
+    decoder: &TriggerDecoder,
+) -> (Option<&'a Event>, Option<&'a Event>) {
+    let onset = &events[..events.len().min(2)];
+    let condition = onset
         .iter()
-        .skip(2)
-        .find(|event| has_bit_set(event.trigger_code, 8) || has_bit_set(event.trigger_code, 9));
+        .find(|event| decoder.decode(event.trigger_code).has_code());
+    let visual = onset.iter().rev().find(|event| {
+        decoder.decode(event.trigger_code).photodiode
+            && condition.is_none_or(|condition| {
+                (event.time_microseconds - condition.time_microseconds).abs()
+                    < TRIAL_START_WINDOW_MICROSECONDS
+            })
+    });
+    (condition, visual)
+}
+
+/// When the stimulus of a trial appeared, and what that was taken from: the
+/// visual onset, or else the condition trigger plus the display lag.
+pub fn stimulus_onset(events: &[Event], options: &ReconstructionOptions) -> (i64, OnsetReference) {
+    match onset_events(events, &options.decoder) {
+        (_, Some(visual)) => (visual.time_microseconds, OnsetReference::Photodiode),
+        (condition, None) => (
+            condition.unwrap_or(&events[0]).time_microseconds + options.display_lag_microseconds,
+            OnsetReference::ConditionTrigger,
+        ),
+    }
+}
+
+/// The button presses of a trial, in order. A trial starts on a non-press
+/// event, so a press right after it, when the trial has no separate visual
+/// onset, answers the stimulus too.
+pub(crate) fn trial_presses<'a>(events: &'a [Event], decoder: &TriggerDecoder) -> Vec<&'a Event> {
+    events
+        .iter()
+        .filter(|event| decoder.decode(event.trigger_code).is_press())
+        .collect::<Vec<_>>()
+}
+
+fn trial_from_response_ready_index(events: &[Event], options: &ReconstructionOptions) -> Trial {
+    let decoder = &options.decoder;
+    let presses = trial_presses(events, decoder);
+    let response = match options.response_policy {
+        ResponsePolicy::First | ResponsePolicy::AnyWrong => presses.first(),
+        ResponsePolicy::Last => presses.last(),
+    };
     let mut condition = Condition::Happy;
     let mut sex = Sex::Male;
-    let mut correct_code = 0;
-    let visual_trigger_mask = 1 << 12;
-    let combined_triggers = events[0].trigger_code | events[1].trigger_code;
-    match combined_triggers & !visual_trigger_mask {
+    let mut correct_button = None;
+    let combined_triggers = decoder.decode(
+        events
+            .iter()
+            .take(2)
+            .filter(|event| !decoder.decode(event.trigger_code).is_press())
+            .fold(0, |codes, event| codes | event.trigger_code),
+    );
+    match combined_triggers.condition_code().unwrap_or(0) {
         21 => {
-            correct_code = 512;
+            correct_button = Some(Button::Two);
             sex = Sex::Female;
             condition = Condition::Angry;
         }
         22 => {
-            correct_code = 512;
+            correct_button = Some(Button::Two);
             sex = Sex::Female;
             condition = Condition::Happy;
         }
         23 => {
-            correct_code = 512;
+            correct_button = Some(Button::Two);
             sex = Sex::Female;
             condition = Condition::Neutral;
         }
         31 => {
-            correct_code = 256;
+            correct_button = Some(Button::One);
             sex = Sex::Male;
             condition = Condition::Angry;
         }
         32 => {
-            correct_code = 256;
+            correct_button = Some(Button::One);
             sex = Sex::Male;
             condition = Condition::Happy;
         }
         33 => {
-            correct_code = 256;
+            correct_button = Some(Button::One);
             sex = Sex::Male;
             condition = Condition::Neutral;
         }
         _ => {}
     }
+    if options.mapping == ResponseMapping::Swapped {
+        correct_button = correct_button.map(Button::other);
+    }
+    let (stimulus_time_microseconds, onset_reference) = stimulus_onset(events, options);
     let mut correct_response = false;
     let mut response_time_milliseconds = None;
+    let is_correct = |event: &Event| {
+        correct_button.is_some() && decoder.decode(event.trigger_code).button() == correct_button
+    };
     if let Some(event) = response {
-        correct_response = event.trigger_code & !visual_trigger_mask == correct_code;
+        correct_response = match options.response_policy {
+            ResponsePolicy::AnyWrong => presses.iter().all(|event| is_correct(event)),
+            ResponsePolicy::First | ResponsePolicy::Last => is_correct(event),
+        };
         if correct_response {
             response_time_milliseconds =
-                Some((event.time_microseconds - events[1].time_microseconds + 500) / 1000);
+                Some((event.time_microseconds - stimulus_time_microseconds + 500) / 1000);
         }
     }
 
@@ -102,46 +290,145 @@ fn trial_from_response_ready_index(events: &[Event]) -> Trial {
         condition,
         sex,
         response_time_milliseconds,
+        stimulus_time_microseconds,
+        button_press_time_microseconds: response.map(|event| event.time_microseconds),
+        artifact: false,
+        block: 1,
+        onset_reference,
+        extra_presses: presses.len().saturating_sub(1),
+        both_buttons: response
+            .is_some_and(|event| decoder.decode(event.trigger_code).both_buttons()),
+        response_button: response.and_then(|event| decoder.decode(event.trigger_code).button()),
+        correct_button,
     }
 }
 
 pub fn reconstruct_trials(events: Vec<Event>) -> Vec<Trial> {
-    let enumerated_nonresponses = events
+    reconstruct_trials_with_options(events, &ReconstructionOptions::default())
+}
+
+fn enumerated_nonresponses<'a>(
+    events: &'a [Event],
+    decoder: &TriggerDecoder,
+) -> Vec<(usize, &'a Event)> {
+    events
         .iter()
         .enumerate()
-        .filter(|(_, event)| {
-            let button1_mask = 1 << 8;
-            let button2_mask = 1 << 9;
-            event.trigger_code & (button1_mask | button2_mask) == 0
-        })
-        .collect::<Vec<_>>();
-    let start_of_trials_indices = enumerated_nonresponses
+        .filter(|(_, event)| !decoder.decode(event.trigger_code).is_press())
+        .collect::<Vec<_>>()
+}
+
+/// Where each trial starts in `events`. A trial runs up to the start of the
+/// next one.
+fn trial_start_indices(events: &[Event], decoder: &TriggerDecoder) -> Vec<usize> {
+    let nonresponses = enumerated_nonresponses(events, decoder);
+    let mut start_indices = nonresponses
         .windows(2)
         .filter(|window| {
             let first_event = window[0].1;
             let second_event = window[1].1;
             let difference_time_microseconds =
                 second_event.time_microseconds - first_event.time_microseconds;
-            (difference_time_microseconds < 100_000
-                && (has_bit_set(first_event.trigger_code, 12)
-                    || has_bit_set(second_event.trigger_code, 12)))
+            (difference_time_microseconds < TRIAL_START_WINDOW_MICROSECONDS
+                && (decoder.decode(first_event.trigger_code).photodiode
+                    || decoder.decode(second_event.trigger_code).photodiode))
                 || difference_time_microseconds > 10_000_000
         })
-        .map(|window| {
-            let first_event_index = window[0].0;
-            first_event_index
-        })
+        .map(|window| window[0].0)
+        .collect::<Vec<_>>();
+    // A condition trigger the photodiode missed starts a trial of its own,
+    // unless it repeats the one before or follows a break, whose start is
+    // already taken.
+    start_indices.extend(
+        nonresponses
+            .iter()
+            .enumerate()
+            .filter(|(position, (_, event))| {
+                let after_previous = position.checked_sub(1).map(|previous| {
+                    event.time_microseconds - nonresponses[previous].1.time_microseconds
+                });
+                let visual_follows = nonresponses[position + 1..]
+                    .iter()
+                    .take_while(|(_, next)| {
+                        next.time_microseconds - event.time_microseconds
+                            < TRIAL_START_WINDOW_MICROSECONDS
+                    })
+                    .any(|(_, next)| decoder.decode(next.trigger_code).photodiode);
+                decoder
+                    .decode(event.trigger_code)
+                    .condition_code()
+                    .is_some_and(|code| CONDITION_CODES.contains(&code))
+                    && after_previous.is_none_or(|difference| {
+                        (TRIAL_START_WINDOW_MICROSECONDS..=10_000_000).contains(&difference)
+                    })
+                    && !visual_follows
+            })
+            .map(|(_, (index, _))| *index),
+    );
+    start_indices.sort_unstable();
+    start_indices.dedup();
+    start_indices
+}
+
+/// The events of each trial, as `trial_start_indices` splits them.
+pub(crate) fn trial_segments<'a>(
+    events: &'a [Event],
+    decoder: &TriggerDecoder,
+) -> Vec<&'a [Event]> {
+    let start_indices = trial_start_indices(events, decoder);
+    let ends = start_indices.iter().skip(1).copied().chain([events.len()]);
+    start_indices
+        .iter()
+        .zip(ends)
+        .map(|(&start, end)| &events[start..end])
+        .collect::<Vec<_>>()
+}
+
+pub fn reconstruct_trials_with_options(
+    events: Vec<Event>,
+    options: &ReconstructionOptions,
+) -> Vec<Trial> {
+    let start_of_trials_indices = trial_start_indices(&events, &options.decoder);
+    let break_indices = enumerated_nonresponses(&events, &options.decoder)
+        .windows(2)
+        .filter(|window| window[1].1.time_microseconds - window[0].1.time_microseconds > 10_000_000)
+        .map(|window| window[0].0)
         .collect::<Vec<_>>();
     let mut trials = start_of_trials_indices
         .windows(2)
-        .map(|indices| trial_from_response_ready_index(&events[indices[0]..indices[1]]))
+        .map(|indices| trial_from_response_ready_index(&events[indices[0]..indices[1]], options))
         .collect::<Vec<_>>();
     trials.push(trial_from_response_ready_index(
         &events[*start_of_trials_indices.last().unwrap()..],
+        options,
     ));
+    let first_start_index = start_of_trials_indices[0];
+    for (trial, start_index) in trials.iter_mut().zip(&start_of_trials_indices) {
+        trial.block = 1 + break_indices
+            .iter()
+            .filter(|&&break_index| first_start_index < break_index && break_index <= *start_index)
+            .count();
+    }
     trials
 }
 
+/// The trials in one condition and face sex. Leaving either unset pools over
+/// it.
+pub fn trials_matching(
+    trials: &[Trial],
+    condition: Option<&Condition>,
+    sex: Option<&Sex>,
+) -> Vec<Trial> {
+    trials
+        .iter()
+        .filter(|trial| {
+            condition.is_none_or(|condition| trial.condition == *condition)
+                && sex.is_none_or(|sex| trial.sex == *sex)
+        })
+        .cloned()
+        .collect::<Vec<_>>()
+}
+
 pub fn accuracy_percentage(trials: &[Trial]) -> f64 {
     100. * trials.iter().filter(|trial| trial.correct_response).count() as f64 / trials.len() as f64
 }
@@ -168,7 +455,8 @@ pub fn reaction_time_milliseconds(trials: &[Trial]) -> Option<i64> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Condition, Event, Sex, Trial};
+    use crate::triggers::Button;
+    use crate::{Condition, Event, OnsetReference, Sex, Trial};
 
     #[test]
     fn parse_events() {
@@ -219,7 +507,16 @@ mod tests {
                 correct_response: true,
                 condition: Condition::Happy,
                 sex: Sex::Female,
-                response_time_milliseconds: Some(7288 - 6302)
+                response_time_milliseconds: Some(7288 - 6302),
+                stimulus_time_microseconds: 6302000,
+                button_press_time_microseconds: Some(7288000),
+                artifact: false,
+                block: 1,
+                onset_reference: OnsetReference::Photodiode,
+                extra_presses: 0,
+                both_buttons: false,
+                response_button: Some(Button::Two),
+                correct_button: Some(Button::Two),
             }],
             trials
         );
@@ -259,13 +556,31 @@ mod tests {
                     correct_response: true,
                     condition: Condition::Happy,
                     sex: Sex::Female,
-                    response_time_milliseconds: Some(8888 - 8199)
+                    response_time_milliseconds: Some(8888 - 8199),
+                    stimulus_time_microseconds: 8199000,
+                    button_press_time_microseconds: Some(8888000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::Two),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(11851 - 11352)
+                    response_time_milliseconds: Some(11851 - 11352),
+                    stimulus_time_microseconds: 11352000,
+                    button_press_time_microseconds: Some(11851000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 }
             ],
             trials
@@ -297,7 +612,16 @@ mod tests {
                 correct_response: true,
                 condition: Condition::Happy,
                 sex: Sex::Female,
-                response_time_milliseconds: Some(6402 - 5063)
+                response_time_milliseconds: Some(6402 - 5063),
+                stimulus_time_microseconds: 5063000,
+                button_press_time_microseconds: Some(6402000),
+                artifact: false,
+                block: 1,
+                onset_reference: OnsetReference::Photodiode,
+                extra_presses: 0,
+                both_buttons: false,
+                response_button: Some(Button::Two),
+                correct_button: Some(Button::Two),
             }],
             trials
         );
@@ -328,7 +652,16 @@ mod tests {
                 correct_response: true,
                 condition: Condition::Happy,
                 sex: Sex::Female,
-                response_time_milliseconds: Some(7104 - 6211)
+                response_time_milliseconds: Some(7104 - 6211),
+                stimulus_time_microseconds: 6211000,
+                button_press_time_microseconds: Some(7104000),
+                artifact: false,
+                block: 1,
+                onset_reference: OnsetReference::Photodiode,
+                extra_presses: 0,
+                both_buttons: false,
+                response_button: Some(Button::Two),
+                correct_button: Some(Button::Two),
             }],
             trials
         );
@@ -368,13 +701,31 @@ mod tests {
                     correct_response: true,
                     condition: Condition::Neutral,
                     sex: Sex::Female,
-                    response_time_milliseconds: Some(18139 - 17691)
+                    response_time_milliseconds: Some(18139 - 17691),
+                    stimulus_time_microseconds: 17691000,
+                    button_press_time_microseconds: Some(18139000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::Two),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Happy,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(21298 - 20860)
+                    response_time_milliseconds: Some(21298 - 20860),
+                    stimulus_time_microseconds: 20860000,
+                    button_press_time_microseconds: Some(21298000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 }
             ],
             trials
@@ -415,13 +766,31 @@ mod tests {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Female,
-                    response_time_milliseconds: Some(27724 - 27207)
+                    response_time_milliseconds: Some(27724 - 27207),
+                    stimulus_time_microseconds: 27207000,
+                    button_press_time_microseconds: Some(27724000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::Two),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Neutral,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(30762 - 30259)
+                    response_time_milliseconds: Some(30762 - 30259),
+                    stimulus_time_microseconds: 30259000,
+                    button_press_time_microseconds: Some(30762000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 }
             ],
             trials
@@ -449,7 +818,16 @@ mod tests {
                 correct_response: true,
                 condition: Condition::Angry,
                 sex: Sex::Female,
-                response_time_milliseconds: Some(125153 - 124555)
+                response_time_milliseconds: Some(125153 - 124555),
+                stimulus_time_microseconds: 124555000,
+                button_press_time_microseconds: Some(125153000),
+                artifact: false,
+                block: 1,
+                onset_reference: OnsetReference::Photodiode,
+                extra_presses: 0,
+                both_buttons: false,
+                response_button: Some(Button::Two),
+                correct_button: Some(Button::Two),
             }],
             trials
         );
@@ -489,13 +867,31 @@ mod tests {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(549)
+                    response_time_milliseconds: Some(549),
+                    stimulus_time_microseconds: 374798016,
+                    button_press_time_microseconds: Some(375347008),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Female,
-                    response_time_milliseconds: Some(697)
+                    response_time_milliseconds: Some(697),
+                    stimulus_time_microseconds: 393036000,
+                    button_press_time_microseconds: Some(393732992),
+                    artifact: false,
+                    block: 2,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::Two),
                 }
             ],
             trials
@@ -523,7 +919,16 @@ mod tests {
                 correct_response: false,
                 condition: Condition::Neutral,
                 sex: Sex::Female,
-                response_time_milliseconds: None
+                response_time_milliseconds: None,
+                stimulus_time_microseconds: 299380992,
+                button_press_time_microseconds: Some(299999008),
+                artifact: false,
+                block: 1,
+                onset_reference: OnsetReference::Photodiode,
+                extra_presses: 0,
+                both_buttons: false,
+                response_button: None,
+                correct_button: Some(Button::Two),
             },],
             trials
         );
@@ -554,7 +959,16 @@ mod tests {
                 correct_response: false,
                 condition: Condition::Neutral,
                 sex: Sex::Female,
-                response_time_milliseconds: None
+                response_time_milliseconds: None,
+                stimulus_time_microseconds: 558033024,
+                button_press_time_microseconds: Some(558448000),
+                artifact: false,
+                block: 1,
+                onset_reference: OnsetReference::Photodiode,
+                extra_presses: 1,
+                both_buttons: false,
+                response_button: Some(Button::One),
+                correct_button: Some(Button::Two),
             },],
             trials
         );
@@ -590,13 +1004,31 @@ mod tests {
                     correct_response: false,
                     condition: Condition::Happy,
                     sex: Sex::Female,
-                    response_time_milliseconds: None
+                    response_time_milliseconds: None,
+                    stimulus_time_microseconds: 636081984,
+                    button_press_time_microseconds: None,
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: None,
+                    correct_button: Some(Button::Two),
                 },
                 Trial {
                     correct_response: false,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: None
+                    response_time_milliseconds: None,
+                    stimulus_time_microseconds: 639201024,
+                    button_press_time_microseconds: Some(639708032),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::One),
                 }
             ],
             trials
@@ -641,13 +1073,31 @@ mod tests {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Female,
-                    response_time_milliseconds: Some(247)
+                    response_time_milliseconds: Some(247),
+                    stimulus_time_microseconds: 696014976,
+                    button_press_time_microseconds: Some(696262016),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::Two),
                 },
                 Trial {
                     correct_response: false,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: None
+                    response_time_milliseconds: None,
+                    stimulus_time_microseconds: 699100992,
+                    button_press_time_microseconds: Some(699731008),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::One),
                 }
             ],
             trials
@@ -675,7 +1125,16 @@ mod tests {
                 correct_response: true,
                 condition: Condition::Angry,
                 sex: Sex::Female,
-                response_time_milliseconds: Some(727)
+                response_time_milliseconds: Some(727),
+                stimulus_time_microseconds: 394620000,
+                button_press_time_microseconds: Some(395347008),
+                artifact: false,
+                block: 1,
+                onset_reference: OnsetReference::Photodiode,
+                extra_presses: 0,
+                both_buttons: false,
+                response_button: Some(Button::Two),
+                correct_button: Some(Button::Two),
             }],
             trials
         );
@@ -710,7 +1169,16 @@ mod tests {
                 correct_response: false,
                 condition: Condition::Neutral,
                 sex: Sex::Female,
-                response_time_milliseconds: None
+                response_time_milliseconds: None,
+                stimulus_time_microseconds: 730995008,
+                button_press_time_microseconds: Some(731918016),
+                artifact: false,
+                block: 1,
+                onset_reference: OnsetReference::Photodiode,
+                extra_presses: 2,
+                both_buttons: false,
+                response_button: Some(Button::One),
+                correct_button: Some(Button::Two),
             }],
             trials
         );
@@ -754,13 +1222,31 @@ mod tests {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Female,
-                    response_time_milliseconds: Some(844)
+                    response_time_milliseconds: Some(844),
+                    stimulus_time_microseconds: 682089984,
+                    button_press_time_microseconds: Some(682934016),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::Two),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Neutral,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(686092 - 685276)
+                    response_time_milliseconds: Some(686092 - 685276),
+                    stimulus_time_microseconds: 685276032,
+                    button_press_time_microseconds: Some(686092032),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 }
             ],
             trials
@@ -805,19 +1291,58 @@ mod tests {
                     correct_response: false,
                     condition: Condition::Angry,
                     sex: Sex::Female,
-                    response_time_milliseconds: None
+                    response_time_milliseconds: None,
+                    stimulus_time_microseconds: 122201000,
+                    button_press_time_microseconds: Some(122553000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 1,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::Two),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(125783 - 125287)
+                    response_time_milliseconds: Some(125783 - 125287),
+                    stimulus_time_microseconds: 125287000,
+                    button_press_time_microseconds: Some(125783000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 }
             ],
             trials
         );
     }
 
+    #[test]
+    fn reconstruct_trials_both_buttons() {
+        let trials = crate::reconstruct_trials(vec![
+            Event {
+                time_microseconds: 122190000,
+                trigger_code: 21,
+            },
+            Event {
+                time_microseconds: 122201000,
+                trigger_code: 4117,
+            },
+            Event {
+                time_microseconds: 122841000,
+                trigger_code: 768,
+            },
+        ]);
+        assert!(trials[0].both_buttons);
+        assert!(!trials[0].correct_response);
+        assert_eq!(Some(122841000), trials[0].button_press_time_microseconds);
+    }
+
     #[test]
     fn reconstruct_trials_responses_masked_by_visual() {
         let trials = crate::reconstruct_trials(vec![
@@ -852,13 +1377,31 @@ mod tests {
                     correct_response: true,
                     condition: Condition::Neutral,
                     sex: Sex::Female,
-                    response_time_milliseconds: Some(76278 - 75655)
+                    response_time_milliseconds: Some(76278 - 75655),
+                    stimulus_time_microseconds: 75655000,
+                    button_press_time_microseconds: Some(76278000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::Two),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Happy,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(79444 - 78706)
+                    response_time_milliseconds: Some(79444 - 78706),
+                    stimulus_time_microseconds: 78706000,
+                    button_press_time_microseconds: Some(79444000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 }
             ],
             trials
@@ -903,13 +1446,31 @@ mod tests {
                     correct_response: true,
                     condition: Condition::Neutral,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(186028 - 185410)
+                    response_time_milliseconds: Some(186028 - 185410),
+                    stimulus_time_microseconds: 185410000,
+                    button_press_time_microseconds: Some(186028000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Neutral,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(189060 - 188612)
+                    response_time_milliseconds: Some(189060 - 188612),
+                    stimulus_time_microseconds: 188612000,
+                    button_press_time_microseconds: Some(189060000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 }
             ],
             trials
@@ -941,7 +1502,16 @@ mod tests {
                 correct_response: true,
                 condition: Condition::Angry,
                 sex: Sex::Male,
-                response_time_milliseconds: Some(932)
+                response_time_milliseconds: Some(932),
+                stimulus_time_microseconds: 548195008,
+                button_press_time_microseconds: Some(549126976),
+                artifact: false,
+                block: 1,
+                onset_reference: OnsetReference::Photodiode,
+                extra_presses: 0,
+                both_buttons: false,
+                response_button: Some(Button::One),
+                correct_button: Some(Button::One),
             }],
             trials
         );
@@ -985,13 +1555,31 @@ mod tests {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(505)
+                    response_time_milliseconds: Some(505),
+                    stimulus_time_microseconds: 626700992,
+                    button_press_time_microseconds: Some(627206016),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Happy,
                     sex: Sex::Female,
-                    response_time_milliseconds: Some(627)
+                    response_time_milliseconds: Some(627),
+                    stimulus_time_microseconds: 629936000,
+                    button_press_time_microseconds: Some(630563008),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::Two),
                 }
             ],
             trials
@@ -1036,13 +1624,31 @@ mod tests {
                     correct_response: true,
                     condition: Condition::Neutral,
                     sex: Sex::Female,
-                    response_time_milliseconds: Some(534)
+                    response_time_milliseconds: Some(534),
+                    stimulus_time_microseconds: 689683008,
+                    button_press_time_microseconds: Some(690217024),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::Two),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(561)
+                    response_time_milliseconds: Some(561),
+                    stimulus_time_microseconds: 692817984,
+                    button_press_time_microseconds: Some(693379008),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 }
             ],
             trials
@@ -1070,7 +1676,16 @@ mod tests {
                 correct_response: true,
                 condition: Condition::Neutral,
                 sex: Sex::Male,
-                response_time_milliseconds: Some(709)
+                response_time_milliseconds: Some(709),
+                stimulus_time_microseconds: 663984000,
+                button_press_time_microseconds: Some(664692992),
+                artifact: false,
+                block: 1,
+                onset_reference: OnsetReference::Photodiode,
+                extra_presses: 0,
+                both_buttons: false,
+                response_button: Some(Button::One),
+                correct_button: Some(Button::One),
             },],
             trials
         );
@@ -1097,7 +1712,16 @@ mod tests {
                 correct_response: true,
                 condition: Condition::Angry,
                 sex: Sex::Female,
-                response_time_milliseconds: Some(730)
+                response_time_milliseconds: Some(730),
+                stimulus_time_microseconds: 720436992,
+                button_press_time_microseconds: Some(721166976),
+                artifact: false,
+                block: 1,
+                onset_reference: OnsetReference::Photodiode,
+                extra_presses: 0,
+                both_buttons: false,
+                response_button: Some(Button::Two),
+                correct_button: Some(Button::Two),
             },],
             trials
         );
@@ -1137,47 +1761,225 @@ mod tests {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(447)
+                    response_time_milliseconds: Some(447),
+                    stimulus_time_microseconds: 376830016,
+                    button_press_time_microseconds: Some(377276992),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Female,
-                    response_time_milliseconds: Some(597)
+                    response_time_milliseconds: Some(597),
+                    stimulus_time_microseconds: 395027008,
+                    button_press_time_microseconds: Some(395624000),
+                    artifact: false,
+                    block: 2,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::Two),
                 },
             ],
             trials
         );
     }
 
+    #[test]
+    fn reconstruct_trials_swapped_mapping() {
+        let trials = crate::reconstruct_trials_with_options(
+            vec![
+                Event {
+                    time_microseconds: 6293000,
+                    trigger_code: 22,
+                },
+                Event {
+                    time_microseconds: 6302000,
+                    trigger_code: 4118,
+                },
+                Event {
+                    time_microseconds: 7288000,
+                    trigger_code: 256,
+                },
+                Event {
+                    time_microseconds: 11342000,
+                    trigger_code: 31,
+                },
+                Event {
+                    time_microseconds: 11352000,
+                    trigger_code: 4127,
+                },
+                Event {
+                    time_microseconds: 11851000,
+                    trigger_code: 256,
+                },
+            ],
+            &crate::ReconstructionOptions {
+                mapping: crate::ResponseMapping::Swapped,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            vec![true, false],
+            trials
+                .iter()
+                .map(|trial| trial.correct_response)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(7288 - 6302), trials[0].response_time_milliseconds);
+        assert_eq!(Some(Button::One), trials[0].correct_button);
+        assert_eq!(Some(Button::One), trials[1].response_button);
+    }
+
+    #[test]
+    fn reconstruct_trials_without_visual() {
+        let events = [
+            (1000000, 31),
+            (1010000, 4127),
+            (1600000, 256),
+            // The photodiode missed a repeated condition trigger.
+            (4000000, 22),
+            (4020000, 22),
+            (4500000, 512),
+            // A lone visual before a break, long before the next condition
+            // trigger, which the photodiode missed too.
+            (6000000, 4096),
+            (21000000, 33),
+            (21700000, 256),
+        ]
+        .into_iter()
+        .map(|(time_microseconds, trigger_code)| Event {
+            time_microseconds,
+            trigger_code,
+        })
+        .collect::<Vec<_>>();
+        let trials = crate::reconstruct_trials_with_options(
+            events,
+            &crate::ReconstructionOptions {
+                display_lag_microseconds: 20000,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            vec![
+                (OnsetReference::Photodiode, 1010000, Some(590), 1),
+                (OnsetReference::ConditionTrigger, 4020000, Some(480), 1),
+                (OnsetReference::ConditionTrigger, 21020000, Some(680), 2),
+            ],
+            trials
+                .iter()
+                .map(|trial| (
+                    trial.onset_reference,
+                    trial.stimulus_time_microseconds,
+                    trial.response_time_milliseconds,
+                    trial.block
+                ))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![Condition::Angry, Condition::Happy, Condition::Neutral],
+            trials
+                .iter()
+                .map(|trial| trial.condition.clone())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn reconstruct_trials_visual_before_condition_trigger() {
+        let trials = crate::reconstruct_trials(vec![
+            Event {
+                time_microseconds: 33000000,
+                trigger_code: 4096,
+            },
+            Event {
+                time_microseconds: 33005000,
+                trigger_code: 23,
+            },
+            Event {
+                time_microseconds: 33500000,
+                trigger_code: 512,
+            },
+        ]);
+        assert_eq!(1, trials.len());
+        assert_eq!(Condition::Neutral, trials[0].condition);
+        assert_eq!(Sex::Female, trials[0].sex);
+        assert_eq!(OnsetReference::Photodiode, trials[0].onset_reference);
+        assert_eq!(33000000, trials[0].stimulus_time_microseconds);
+        assert_eq!(Some(500), trials[0].response_time_milliseconds);
+    }
+
     #[test]
     fn accuracy() {
         assert_eq!(
             75.,
-            crate::accuracy_percentage(&vec![
+            crate::accuracy_percentage(&[
                 Trial {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(447)
+                    response_time_milliseconds: Some(447),
+                    stimulus_time_microseconds: 0,
+                    button_press_time_microseconds: Some(447000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Female,
-                    response_time_milliseconds: Some(597)
+                    response_time_milliseconds: Some(597),
+                    stimulus_time_microseconds: 0,
+                    button_press_time_microseconds: Some(597000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::Two),
                 },
                 Trial {
                     correct_response: false,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(447)
+                    response_time_milliseconds: Some(447),
+                    stimulus_time_microseconds: 0,
+                    button_press_time_microseconds: Some(447000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::One),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Female,
-                    response_time_milliseconds: Some(597)
+                    response_time_milliseconds: Some(597),
+                    stimulus_time_microseconds: 0,
+                    button_press_time_microseconds: Some(597000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::Two),
                 },
             ])
         )
@@ -1187,24 +1989,51 @@ mod tests {
     fn reaction_time() {
         assert_eq!(
             Some((447 + 214 + 1) / 2),
-            crate::reaction_time_milliseconds(&vec![
+            crate::reaction_time_milliseconds(&[
                 Trial {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: Some(447)
+                    response_time_milliseconds: Some(447),
+                    stimulus_time_microseconds: 0,
+                    button_press_time_microseconds: Some(447000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::One),
+                    correct_button: Some(Button::One),
                 },
                 Trial {
                     correct_response: false,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: None
+                    response_time_milliseconds: None,
+                    stimulus_time_microseconds: 0,
+                    button_press_time_microseconds: None,
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: None,
+                    correct_button: Some(Button::One),
                 },
                 Trial {
                     correct_response: true,
                     condition: Condition::Angry,
                     sex: Sex::Female,
-                    response_time_milliseconds: Some(214)
+                    response_time_milliseconds: Some(214),
+                    stimulus_time_microseconds: 0,
+                    button_press_time_microseconds: Some(214000),
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: Some(Button::Two),
+                    correct_button: Some(Button::Two),
                 },
             ])
         )
@@ -1214,24 +2043,51 @@ mod tests {
     fn reaction_time_all_wrong() {
         assert_eq!(
             None,
-            crate::reaction_time_milliseconds(&vec![
+            crate::reaction_time_milliseconds(&[
                 Trial {
                     correct_response: false,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: None
+                    response_time_milliseconds: None,
+                    stimulus_time_microseconds: 0,
+                    button_press_time_microseconds: None,
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: None,
+                    correct_button: Some(Button::One),
                 },
                 Trial {
                     correct_response: false,
                     condition: Condition::Angry,
                     sex: Sex::Male,
-                    response_time_milliseconds: None
+                    response_time_milliseconds: None,
+                    stimulus_time_microseconds: 0,
+                    button_press_time_microseconds: None,
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: None,
+                    correct_button: Some(Button::One),
                 },
                 Trial {
                     correct_response: false,
                     condition: Condition::Angry,
                     sex: Sex::Female,
-                    response_time_milliseconds: None
+                    response_time_milliseconds: None,
+                    stimulus_time_microseconds: 0,
+                    button_press_time_microseconds: None,
+                    artifact: false,
+                    block: 1,
+                    onset_reference: OnsetReference::Photodiode,
+                    extra_presses: 0,
+                    both_buttons: false,
+                    response_button: None,
+                    correct_button: Some(Button::Two),
                 },
             ])
         )
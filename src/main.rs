@@ -1,14 +1,163 @@
 use emotional_faces_recode::{
-    accuracy_percentage, parse_events, reconstruct_trials, Condition, Sex, Trial,
+    accuracy_percentage, anova,
+    bootstrap::Bootstrap,
+    composites::{self, BalancedIntegration, Composite},
+    contrasts::Contrast,
+    demographics::{self, Demographics, SwapRule},
+    diffusion::{ez_diffusion, EzDiffusion, EzUndefined},
+    ex_gaussian::{fit_ex_gaussian, ExGaussian},
+    exclusions::Exclusions,
+    group, parse_events, recording,
+    response_mapping::{self, MappingOverride, MappingSources},
+    response_window::ResponseTiming,
+    sequential::SequentialEffect,
+    signal_detection::{signal_detection, SignalDetection},
+    simulation::{CellSetting, ReactionTimeDistribution, Simulation},
+    statistics::{ReactionTimeStatistic, ReactionTimeSummary},
+    time_on_task::{self, Period},
+    timing::{self, TimingMeasure},
+    triggers::{self, TriggerDecoder},
+    trimming::Trimming,
+    validation, Condition, ReconstructionOptions, ResponseMapping, Sex, Trial,
 };
 use std::io::Write;
 
-fn trials_matching(trials: &[Trial], condition: Condition, sex: Sex) -> Vec<Trial> {
-    trials
-        .iter()
-        .cloned()
-        .filter(|trial| trial.condition == condition && trial.sex == sex)
-        .collect::<Vec<_>>()
+const USAGE: &str = "usage: emotional-faces-recode <input directory> <output file> [options]
+       emotional-faces-recode validate <input directory> <report file> [--trigger-lines <lines>]
+       emotional-faces-recode timing <input directory> <report file> [--trigger-lines <lines>]
+                                     [--display-lag <ms>]
+       emotional-faces-recode inspect <.evt file> [--trigger-lines <lines>]
+       emotional-faces-recode simulate <.evt file> [simulation options]
+
+validate checks every recording before analysis and writes a pass, warn or fail
+status per file, with the checks behind it, to the report file.
+
+timing writes the distribution of condition trigger to visual onset latency,
+inter-stimulus interval and response latency per file, with the trials outside
+3 interquartile ranges of the quartiles (and over a 60 Hz frame from the median).
+Response latency is measured from the same onset as reaction time.
+
+inspect prints every trigger of one recording with the trial it falls in and the
+lines it decodes to.
+
+simulate writes a synthetic recording of the paradigm, with known trials, for
+checking the analysis before real data is collected.
+
+options:
+    --trigger-lines <lines>     which bits carry which trigger line at this site, comma
+                                separated from stimulus-bits=<n> (the stimulus code is the
+                                lowest n bits), button1=<bit>, button2=<bit> and
+                                photodiode=<bit>. By default stimulus-bits=8, button1=8,
+                                button2=9, photodiode=12
+    --display-lag <ms>          measure reaction time from the condition trigger plus this
+                                lag in trials without a visual onset (photodiode) trigger
+    --response-policy <policy>  which button press scores a trial with several: first (the
+                                default), last, or any-wrong to score it on the first but
+                                as an error if any press was wrong
+    --exclude-artifacts         leave trials overlapping an artifact segment out of summaries
+    --exclude-both-buttons      leave presses of both buttons at once (768) out of accuracy
+                                instead of counting them as errors. With either exclusion
+                                the trials left out are counted per cell
+    --count-both-buttons        count presses of both buttons at once (768) per cell
+    --min-rt <ms>               responses faster than this are anticipatory
+    --max-rt <ms>               responses slower than this are late
+    --out-of-window-as-errors   score anticipatory and late responses as errors
+    --trim <strategy>           trim reaction times per cell: none, sd:<cutoff> or mad:<cutoff>
+    --rt-statistics <list>      extra reaction time columns, comma separated from
+                                n, mean, median, sd, iqr, p10, p90 and cv
+    --composites <list>         speed-accuracy composite columns, comma separated from
+                                ies, rcs and bis
+    --signal-detection          d', criterion c and ln(beta) per emotion, male faces as signal
+    --ez-diffusion              EZ-diffusion drift rate, boundary separation and
+                                non-decision time, with any 100% accuracy correction, or
+                                why a cell has no estimates, such as 0% or 50% accuracy
+    --ex-gaussian               maximum-likelihood ex-Gaussian mu, sigma and tau of correct
+                                reaction times, with the fit's log-likelihood, iterations
+                                and whether it converged
+    --contrasts <list>          accuracy and reaction time difference columns, comma
+                                separated, such as \"Angry-Neutral,Happy Male-Happy Female\"
+                                or \"(Angry Male-Neutral Male)-(Angry Female-Neutral Female)\"
+    --time-on-task              accuracy and reaction time per block and per session quartile
+    --trial-output <file>       also write every trial, tagged with its block, to this file
+    --sequential                post-error slowing and emotion and face sex switch costs
+                                in reaction time and accuracy, by the current trial's cell
+    --group-output <file>       write the mean, SD, SE and 95% CI of every column across
+                                participants to this file
+    --demographics <file>       CSV of participant information, subject ID (the .evt file
+                                name without extension) in the first column, joined onto
+                                the summary and trial outputs
+    --group-by <column>         demographics column to split the group output by
+    --swap-buttons-when <column>=<value>
+                                score participants with this demographics value, such as
+                                Handedness=left, with the male and female buttons swapped
+    --swap-buttons <subjects>   comma separated subject IDs to score with swapped buttons
+    --mapping <mapping>         score every other file with the standard or swapped
+                                mapping, or auto (the default) to take each file's from
+                                --mapping-table, --swap-buttons-when or an .evt marker
+    --mapping-table <file>      CSV of subject IDs and their response mapping, standard or
+                                swapped. Otherwise a \"Response mapping: swapped\" comment
+                                in the .evt file is used, then the standard mapping
+    --fix-swapped               re-score files that look like they were recorded with the
+                                buttons swapped (accuracy far below chance, but normal when
+                                swapped) instead of only warning about them. Either way the
+                                summary records the mapping of every file, and which ones
+                                looked swapped, once any file does
+    --bootstrap <resamples>     percentile 95% CIs of each participant's accuracy and
+                                reaction time, resampling trials within each emotion by
+                                face sex cell, also for the pooled All columns
+    --seed <n>                  random seed for --bootstrap, 1 by default
+    --anova-output <file>       write a 3 (emotion) x 2 (face sex) repeated-measures ANOVA
+                                on accuracy and reaction time to this file
+
+simulation options:
+    --trials <n>                240 by default
+    --blocks <n>                separated by 15 s breaks, 2 by default
+    --order <order>             random (the default, balanced across cells), cycled or
+                                blocked
+    --rt [<cell>=]<mu>,<sigma>,<tau>
+                                ex-Gaussian reaction time distribution in ms, for one cell
+                                such as \"Angry Male\" or for all of them. 500,60,120 by
+                                default. Can be repeated
+    --error-rate [<cell>=]<p>   proportion of wrong button presses, 0.08 by default. Can be
+                                repeated
+    --duplicate-triggers <p>    per-trial probability of a duplicated condition trigger
+    --lone-visuals <p>          per-trial probability of an extra visual onset
+    --missing-responses <p>     per-trial probability of no button press
+    --both-buttons <p>          per-trial probability of both buttons pressed at once.
+                                Probabilities are between 0 and 1, and missing responses
+                                and both buttons together at most 1
+    --seed <n>                  1 by default
+    --trial-output <file>       also write the simulated trials to this file";
+
+/// A named group of trials, matching on condition and face sex where given.
+type Cell = (&'static str, Option<Condition>, Option<Sex>);
+
+const CELLS: [Cell; 7] = [
+    ("All", None, None),
+    ("Angry Male", Some(Condition::Angry), Some(Sex::Male)),
+    ("Happy Male", Some(Condition::Happy), Some(Sex::Male)),
+    ("Neutral Male", Some(Condition::Neutral), Some(Sex::Male)),
+    ("Angry Female", Some(Condition::Angry), Some(Sex::Female)),
+    ("Happy Female", Some(Condition::Happy), Some(Sex::Female)),
+    (
+        "Neutral Female",
+        Some(Condition::Neutral),
+        Some(Sex::Female),
+    ),
+];
+
+const EMOTIONS: [Cell; 4] = [
+    ("All", None, None),
+    ("Angry", Some(Condition::Angry), None),
+    ("Happy", Some(Condition::Happy), None),
+    ("Neutral", Some(Condition::Neutral), None),
+];
+
+/// For values reported once per participant rather than per cell.
+const PARTICIPANT: [Cell; 1] = [("", None, None)];
+
+fn trials_matching(trials: &[Trial], (_, condition, sex): &Cell) -> Vec<Trial> {
+    emotional_faces_recode::trials_matching(trials, condition.as_ref(), sex.as_ref())
 }
 
 fn reaction_time_milliseconds(trials: &[Trial]) -> String {
@@ -19,35 +168,1007 @@ fn reaction_time_milliseconds(trials: &[Trial]) -> String {
     }
 }
 
-fn main() {
-    let args = std::env::args().collect::<Vec<_>>();
-    let input_directory = &args[1];
-    let output_file_path = &args[2];
-    let mut output_file = match std::fs::File::create(&output_file_path) {
-        Err(why) => panic!("couldn't create {}: {}", output_file_path, why),
+struct Options {
+    input_directory: String,
+    output_file_path: String,
+    reconstruction: ReconstructionOptions,
+    exclusions: Exclusions,
+    count_both_buttons: bool,
+    reaction_time_statistics: Vec<ReactionTimeStatistic>,
+    composites: Vec<Composite>,
+    signal_detection: bool,
+    ez_diffusion: bool,
+    ex_gaussian: bool,
+    contrasts: Vec<Contrast>,
+    time_on_task: bool,
+    trial_output_file_path: Option<String>,
+    sequential: bool,
+    group_output_file_path: Option<String>,
+    demographics_file_path: Option<String>,
+    group_by: Option<String>,
+    swap_rule: Option<SwapRule>,
+    swapped_subjects: Vec<String>,
+    mapping: MappingOverride,
+    mapping_table_file_path: Option<String>,
+    fix_swapped: bool,
+    anova_output_file_path: Option<String>,
+    bootstrap: Option<Bootstrap>,
+}
+
+impl Options {
+    /// Whether the summary records each file's response mapping and where it
+    /// came from.
+    fn records_mapping(&self) -> bool {
+        self.fix_swapped
+            || self.swap_rule.is_some()
+            || !self.swapped_subjects.is_empty()
+            || self.mapping != MappingOverride::Auto
+            || self.mapping_table_file_path.is_some()
+    }
+}
+
+fn option_value<'a, T: std::str::FromStr>(
+    arguments: &mut impl Iterator<Item = &'a String>,
+    option: &str,
+) -> T {
+    match arguments.next().map(|value| value.parse::<T>()) {
+        Some(Ok(value)) => value,
+        _ => panic!("{} requires a valid value\n{}", option, USAGE),
+    }
+}
+
+fn option_list<'a, T>(arguments: &mut impl Iterator<Item = &'a String>, option: &str) -> Vec<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    option_value::<String>(arguments, option)
+        .split(',')
+        .map(|name| {
+            name.trim()
+                .parse::<T>()
+                .unwrap_or_else(|why| panic!("{}\n{}", why, USAGE))
+        })
+        .collect::<Vec<_>>()
+}
+
+fn parse_options(args: &[String]) -> Options {
+    let mut positional = Vec::new();
+    let mut reconstruction = ReconstructionOptions::default();
+    let mut exclusions = Exclusions::default();
+    let mut count_both_buttons = false;
+    let mut reaction_time_statistics = Vec::new();
+    let mut composites = Vec::new();
+    let mut signal_detection = false;
+    let mut ez_diffusion = false;
+    let mut ex_gaussian = false;
+    let mut contrasts = Vec::new();
+    let mut time_on_task = false;
+    let mut trial_output_file_path = None;
+    let mut sequential = false;
+    let mut group_output_file_path = None;
+    let mut demographics_file_path = None;
+    let mut group_by = None;
+    let mut swap_rule = None;
+    let mut swapped_subjects = Vec::new();
+    let mut mapping = MappingOverride::Auto;
+    let mut mapping_table_file_path = None;
+    let mut fix_swapped = false;
+    let mut anova_output_file_path = None;
+    let mut bootstrap_resamples = None;
+    let mut seed = Bootstrap::default().seed;
+    let mut arguments = args.iter().skip(1);
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--display-lag" => {
+                reconstruction.display_lag_microseconds =
+                    option_value::<i64>(&mut arguments, argument) * 1000
+            }
+            "--trigger-lines" => reconstruction.decoder = option_value(&mut arguments, argument),
+            "--response-policy" => {
+                reconstruction.response_policy = option_value(&mut arguments, argument)
+            }
+            "--exclude-artifacts" => exclusions.artifacts = true,
+            "--exclude-both-buttons" => exclusions.both_buttons = true,
+            "--count-both-buttons" => count_both_buttons = true,
+            "--min-rt" => {
+                exclusions.response_window.minimum_milliseconds =
+                    Some(option_value(&mut arguments, argument))
+            }
+            "--max-rt" => {
+                exclusions.response_window.maximum_milliseconds =
+                    Some(option_value(&mut arguments, argument))
+            }
+            "--out-of-window-as-errors" => exclusions.out_of_window_as_errors = true,
+            "--trim" => exclusions.trimming = option_value(&mut arguments, argument),
+            "--rt-statistics" => reaction_time_statistics = option_list(&mut arguments, argument),
+            "--composites" => composites = option_list(&mut arguments, argument),
+            "--signal-detection" => signal_detection = true,
+            "--ez-diffusion" => ez_diffusion = true,
+            "--ex-gaussian" => ex_gaussian = true,
+            "--contrasts" => contrasts = option_list(&mut arguments, argument),
+            "--time-on-task" => time_on_task = true,
+            "--sequential" => sequential = true,
+            "--group-output" => {
+                group_output_file_path = Some(option_value(&mut arguments, argument))
+            }
+            "--demographics" => {
+                demographics_file_path = Some(option_value(&mut arguments, argument))
+            }
+            "--group-by" => group_by = Some(option_value(&mut arguments, argument)),
+            "--swap-buttons-when" => swap_rule = Some(option_value(&mut arguments, argument)),
+            "--swap-buttons" => swapped_subjects = option_list(&mut arguments, argument),
+            "--mapping" => mapping = option_value(&mut arguments, argument),
+            "--fix-swapped" => fix_swapped = true,
+            "--mapping-table" => {
+                mapping_table_file_path = Some(option_value(&mut arguments, argument))
+            }
+            "--bootstrap" => bootstrap_resamples = Some(option_value(&mut arguments, argument)),
+            "--seed" => seed = option_value(&mut arguments, argument),
+            "--anova-output" => {
+                anova_output_file_path = Some(option_value(&mut arguments, argument))
+            }
+            "--trial-output" => {
+                trial_output_file_path = Some(option_value(&mut arguments, argument))
+            }
+            option if option.starts_with("--") => panic!("unknown option {}\n{}", option, USAGE),
+            _ => positional.push(argument.clone()),
+        }
+    }
+    if positional.len() != 2 {
+        panic!("{}", USAGE);
+    }
+    if group_by.is_some() && demographics_file_path.is_none() {
+        panic!("--group-by requires --demographics\n{}", USAGE);
+    }
+    if swap_rule.is_some() && demographics_file_path.is_none() {
+        panic!("--swap-buttons-when requires --demographics\n{}", USAGE);
+    }
+    Options {
+        input_directory: positional[0].clone(),
+        output_file_path: positional[1].clone(),
+        reconstruction,
+        exclusions,
+        count_both_buttons,
+        reaction_time_statistics,
+        composites,
+        signal_detection,
+        ez_diffusion,
+        ex_gaussian,
+        contrasts,
+        time_on_task,
+        trial_output_file_path,
+        sequential,
+        group_output_file_path,
+        demographics_file_path,
+        group_by,
+        swap_rule,
+        swapped_subjects,
+        mapping,
+        mapping_table_file_path,
+        fix_swapped,
+        anova_output_file_path,
+        bootstrap: bootstrap_resamples.map(|resamples| Bootstrap { resamples, seed }),
+    }
+}
+
+type CellValue = Box<dyn Fn(&[Trial]) -> Vec<String>>;
+type SignalDetectionStatistic = fn(&SignalDetection) -> f64;
+type EzDiffusionStatistic = fn(&Result<EzDiffusion, EzUndefined>) -> String;
+type ExGaussianStatistic = fn(&ExGaussian) -> String;
+
+/// Values reported for every cell, one output column per name and cell.
+/// Values computed together, such as the bounds of one interval, share a
+/// measure and come out next to each other in each cell.
+struct Measure {
+    names: Vec<String>,
+    cells: &'static [Cell],
+    value: CellValue,
+}
+
+/// A measure with a single value per cell.
+fn one(value: impl Fn(&[Trial]) -> String + 'static) -> CellValue {
+    Box::new(move |trials| vec![value(trials)])
+}
+
+fn format_optional(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("{:.2}", value),
+        None => "NaN".to_string(),
+    }
+}
+
+/// Every participant's six condition cells, which balanced integration
+/// scores are standardized against. The "All" cell is scored on that same
+/// scale.
+fn balanced_integration(
+    exclusions: &Exclusions,
+    participants: &[(String, Vec<Trial>)],
+) -> Option<BalancedIntegration> {
+    let observations = participants
+        .iter()
+        .flat_map(|(_, trials)| {
+            CELLS
+                .iter()
+                .filter(|(_, condition, _)| condition.is_some())
+                .map(|cell| {
+                    let cell_trials = trials_matching(trials, cell);
+                    (
+                        exclusions.accuracy_trials(&cell_trials),
+                        exclusions.reaction_time_trials(&cell_trials),
+                    )
+                })
+        })
+        .collect::<Vec<_>>();
+    BalancedIntegration::from_observations(&observations)
+}
+
+/// Lower and upper bootstrap confidence limit columns for one measure, from
+/// one bootstrap per cell.
+fn bootstrap_measure(
+    name: &str,
+    unit: &str,
+    bootstrap: &Bootstrap,
+    exclusions: &Exclusions,
+    measure: fn(&Exclusions, &[Trial]) -> Option<f64>,
+) -> Measure {
+    let bootstrap = bootstrap.clone();
+    let exclusions = exclusions.clone();
+    Measure {
+        names: ["Lower", "Upper"]
+            .iter()
+            .map(|bound| format!("{} 95% CI {} ({})", name, bound, unit))
+            .collect::<Vec<_>>(),
+        cells: &CELLS,
+        value: Box::new(move |trials| {
+            let interval =
+                bootstrap.confidence_interval_95(trials, |resample| measure(&exclusions, resample));
+            vec![
+                format_optional(interval.map(|interval| interval.lower)),
+                format_optional(interval.map(|interval| interval.upper)),
+            ]
+        }),
+    }
+}
+
+fn measures(options: &Options, participants: &[(String, Vec<Trial>)]) -> Vec<Measure> {
+    let accuracy_exclusions = options.exclusions.clone();
+    let reaction_time_exclusions = options.exclusions.clone();
+    let mut measures = vec![
+        Measure {
+            names: vec!["Accuracy (%)".to_string()],
+            cells: &CELLS,
+            value: one(move |trials| {
+                format!(
+                    "{:.2}",
+                    accuracy_percentage(&accuracy_exclusions.accuracy_trials(trials))
+                )
+            }),
+        },
+        Measure {
+            names: vec!["Reaction Time (ms)".to_string()],
+            cells: &CELLS,
+            value: one(move |trials| {
+                reaction_time_milliseconds(&reaction_time_exclusions.reaction_time_trials(trials))
+            }),
+        },
+    ];
+    if let Some(bootstrap) = &options.bootstrap {
+        // Each interval goes straight after its point estimate.
+        let reaction_time_interval = bootstrap_measure(
+            "Reaction Time",
+            "ms",
+            bootstrap,
+            &options.exclusions,
+            |exclusions, trials| {
+                emotional_faces_recode::reaction_time_milliseconds(
+                    &exclusions.reaction_time_trials(trials),
+                )
+                .map(|milliseconds| milliseconds as f64)
+            },
+        );
+        measures.push(reaction_time_interval);
+        let accuracy_interval = bootstrap_measure(
+            "Accuracy",
+            "%",
+            bootstrap,
+            &options.exclusions,
+            |exclusions, trials| Some(accuracy_percentage(&exclusions.accuracy_trials(trials))),
+        );
+        measures.insert(1, accuracy_interval);
+    }
+    for statistic in &options.reaction_time_statistics {
+        let exclusions = options.exclusions.clone();
+        let statistic = statistic.clone();
+        measures.push(Measure {
+            names: vec![statistic.name().to_string()],
+            cells: &CELLS,
+            value: one(move |trials| {
+                let summary =
+                    ReactionTimeSummary::from_trials(&exclusions.reaction_time_trials(trials));
+                match (
+                    &statistic,
+                    summary.and_then(|summary| summary.get(&statistic)),
+                ) {
+                    (ReactionTimeStatistic::Count, value) => value.unwrap_or(0.).to_string(),
+                    (ReactionTimeStatistic::CoefficientOfVariation, Some(value)) => {
+                        format!("{:.4}", value)
+                    }
+                    (_, value) => format_optional(value),
+                }
+            }),
+        });
+    }
+    for composite in &options.composites {
+        let exclusions = options.exclusions.clone();
+        let value: CellValue = match composite {
+            Composite::InverseEfficiency => one(move |trials| {
+                format_optional(composites::inverse_efficiency_score(
+                    &exclusions.accuracy_trials(trials),
+                    &exclusions.reaction_time_trials(trials),
+                ))
+            }),
+            Composite::RateCorrect => one(move |trials| {
+                format_optional(composites::rate_correct_score(
+                    &exclusions.accuracy_trials(trials),
+                ))
+            }),
+            Composite::BalancedIntegration => {
+                let balanced_integration = balanced_integration(&exclusions, participants);
+                one(move |trials| {
+                    format_optional(balanced_integration.as_ref().and_then(
+                        |balanced_integration| {
+                            balanced_integration.score(
+                                &exclusions.accuracy_trials(trials),
+                                &exclusions.reaction_time_trials(trials),
+                            )
+                        },
+                    ))
+                })
+            }
+        };
+        measures.push(Measure {
+            names: vec![composite.name().to_string()],
+            cells: &CELLS,
+            value,
+        });
+    }
+    if options.exclusions.artifacts || options.exclusions.both_buttons {
+        let exclusions = options.exclusions.clone();
+        measures.push(Measure {
+            names: vec!["Excluded Trials".to_string()],
+            cells: &CELLS,
+            value: one(move |trials| exclusions.excluded_count(trials).to_string()),
+        });
+    }
+    if options.count_both_buttons {
+        measures.push(Measure {
+            names: vec!["Both Button Trials".to_string()],
+            cells: &CELLS,
+            value: one(|trials| {
+                trials
+                    .iter()
+                    .filter(|trial| trial.both_buttons)
+                    .count()
+                    .to_string()
+            }),
+        });
+    }
+    let response_window = &options.exclusions.response_window;
+    for (name, timing, bounded) in [
+        (
+            "Anticipatory Trials",
+            ResponseTiming::Anticipatory,
+            response_window.minimum_milliseconds.is_some(),
+        ),
+        (
+            "Late Trials",
+            ResponseTiming::Late,
+            response_window.maximum_milliseconds.is_some(),
+        ),
+    ] {
+        if bounded {
+            let response_window = response_window.clone();
+            measures.push(Measure {
+                names: vec![name.to_string()],
+                cells: &CELLS,
+                value: one(move |trials| response_window.count(trials, timing.clone()).to_string()),
+            });
+        }
+    }
+    if options.exclusions.trimming != Trimming::None {
+        let exclusions = options.exclusions.clone();
+        measures.push(Measure {
+            names: vec![format!("Trimmed Trials ({})", options.exclusions.trimming)],
+            cells: &CELLS,
+            value: one(move |trials| exclusions.trimmed_count(trials).to_string()),
+        });
+    }
+    if options.signal_detection {
+        let statistics: [(&str, SignalDetectionStatistic); 3] = [
+            ("d'", |detection| detection.d_prime),
+            ("Criterion c", |detection| detection.criterion),
+            ("ln(beta)", |detection| detection.log_beta),
+        ];
+        for (name, statistic) in statistics {
+            let exclusions = options.exclusions.clone();
+            measures.push(Measure {
+                names: vec![name.to_string()],
+                cells: &EMOTIONS,
+                value: one(move |trials| {
+                    let included = trials
+                        .iter()
+                        .filter(|trial| !exclusions.excludes(trial))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    format_optional(signal_detection(&included).as_ref().map(statistic))
+                }),
+            });
+        }
+    }
+    if options.ez_diffusion {
+        let statistics: [(&str, EzDiffusionStatistic); 4] = [
+            ("EZ Drift Rate", |diffusion| match diffusion {
+                Ok(diffusion) => format!("{:.4}", diffusion.drift_rate),
+                Err(_) => "NaN".to_string(),
+            }),
+            ("EZ Boundary Separation", |diffusion| match diffusion {
+                Ok(diffusion) => format!("{:.4}", diffusion.boundary_separation),
+                Err(_) => "NaN".to_string(),
+            }),
+            ("EZ Non-Decision Time (ms)", |diffusion| match diffusion {
+                Ok(diffusion) => format!("{:.2}", diffusion.non_decision_time_milliseconds),
+                Err(_) => "NaN".to_string(),
+            }),
+            // Which correction was made, or why there are no estimates.
+            ("EZ Accuracy Correction", |diffusion| match diffusion {
+                Ok(diffusion) => diffusion.edge_correction.name().to_string(),
+                Err(why) => format!("no estimate: {}", why.name()),
+            }),
+        ];
+        for (name, statistic) in statistics {
+            let exclusions = options.exclusions.clone();
+            measures.push(Measure {
+                names: vec![name.to_string()],
+                cells: &CELLS,
+                value: one(move |trials| {
+                    statistic(&ez_diffusion(
+                        &exclusions.accuracy_trials(trials),
+                        &exclusions.reaction_time_trials(trials),
+                    ))
+                }),
+            });
+        }
+    }
+    if options.ex_gaussian {
+        let statistics: [(&str, ExGaussianStatistic); 6] = [
+            ("Ex-Gaussian Mu (ms)", |fit| format!("{:.2}", fit.mu)),
+            ("Ex-Gaussian Sigma (ms)", |fit| format!("{:.2}", fit.sigma)),
+            ("Ex-Gaussian Tau (ms)", |fit| format!("{:.2}", fit.tau)),
+            ("Ex-Gaussian Log-Likelihood", |fit| {
+                format!("{:.2}", fit.log_likelihood)
+            }),
+            ("Ex-Gaussian Iterations", |fit| fit.iterations.to_string()),
+            ("Ex-Gaussian Converged", |fit| fit.converged.to_string()),
+        ];
+        for (name, statistic) in statistics {
+            let exclusions = options.exclusions.clone();
+            measures.push(Measure {
+                names: vec![name.to_string()],
+                cells: &CELLS,
+                value: one(move |trials| {
+                    fit_ex_gaussian(&exclusions.reaction_time_trials(trials))
+                        .as_ref()
+                        .map_or("NaN".to_string(), statistic)
+                }),
+            });
+        }
+    }
+    for contrast in &options.contrasts {
+        let accuracy_exclusions = options.exclusions.clone();
+        let accuracy_contrast = contrast.clone();
+        let reaction_time_exclusions = options.exclusions.clone();
+        let reaction_time_contrast = contrast.clone();
+        measures.push(Measure {
+            names: vec![format!("{} Accuracy (%)", contrast.name)],
+            cells: &PARTICIPANT,
+            value: one(move |trials| {
+                format_optional(accuracy_contrast.evaluate(trials, |trials| {
+                    Some(accuracy_percentage(
+                        &accuracy_exclusions.accuracy_trials(trials),
+                    ))
+                }))
+            }),
+        });
+        measures.push(Measure {
+            names: vec![format!("{} Reaction Time (ms)", contrast.name)],
+            cells: &PARTICIPANT,
+            value: one(move |trials| {
+                // From the unrounded means, rounded only for the output.
+                format_optional(reaction_time_contrast.evaluate(trials, |trials| {
+                    ReactionTimeSummary::from_trials(
+                        &reaction_time_exclusions.reaction_time_trials(trials),
+                    )
+                    .map(|summary| summary.mean)
+                }))
+            }),
+        });
+    }
+    if options.time_on_task {
+        let block_count = participants
+            .iter()
+            .map(|(_, trials)| time_on_task::block_count(trials))
+            .max()
+            .unwrap_or(0);
+        let periods = (1..=block_count)
+            .map(Period::Block)
+            .chain((1..=4).map(Period::Quartile));
+        for period in periods {
+            let accuracy_period = period.clone();
+            let accuracy_exclusions = options.exclusions.clone();
+            let reaction_time_exclusions = options.exclusions.clone();
+            measures.push(Measure {
+                names: vec![format!("{} Accuracy (%)", period)],
+                cells: &PARTICIPANT,
+                value: one(move |trials| {
+                    format!(
+                        "{:.2}",
+                        accuracy_percentage(
+                            &accuracy_exclusions.accuracy_trials(&accuracy_period.trials(trials))
+                        )
+                    )
+                }),
+            });
+            measures.push(Measure {
+                names: vec![format!("{} Reaction Time (ms)", period)],
+                cells: &PARTICIPANT,
+                value: one(move |trials| {
+                    reaction_time_milliseconds(
+                        &reaction_time_exclusions.reaction_time_trials(&period.trials(trials)),
+                    )
+                }),
+            });
+        }
+    }
+    if options.sequential {
+        for effect in SequentialEffect::ALL {
+            for cell in &CELLS {
+                let reaction_time_effect = effect.clone();
+                let reaction_time_exclusions = options.exclusions.clone();
+                measures.push(Measure {
+                    names: vec![format!("{} {} RT (ms)", cell.0, effect.name())],
+                    cells: &PARTICIPANT,
+                    value: one(move |trials| {
+                        format_optional(reaction_time_effect.difference(trials, |group| {
+                            emotional_faces_recode::reaction_time_milliseconds(
+                                &reaction_time_exclusions
+                                    .reaction_time_trials(&trials_matching(group, cell)),
+                            )
+                            .map(|milliseconds| milliseconds as f64)
+                        }))
+                    }),
+                });
+                let accuracy_effect = effect.clone();
+                let accuracy_exclusions = options.exclusions.clone();
+                measures.push(Measure {
+                    names: vec![format!("{} {} Accuracy (%)", cell.0, effect.name())],
+                    cells: &PARTICIPANT,
+                    value: one(move |trials| {
+                        format_optional(accuracy_effect.difference(trials, |group| {
+                            let accuracy_trials =
+                                accuracy_exclusions.accuracy_trials(&trials_matching(group, cell));
+                            if accuracy_trials.is_empty() {
+                                None
+                            } else {
+                                Some(accuracy_percentage(&accuracy_trials))
+                            }
+                        }))
+                    }),
+                });
+            }
+        }
+    }
+    measures
+}
+
+/// Demographics columns, if given, are joined on after the trial columns.
+fn write_trials(
+    file_path: &str,
+    participants: &[(String, Vec<Trial>)],
+    demographics: Option<&Demographics>,
+) {
+    let mut file = match std::fs::File::create(file_path) {
+        Err(why) => panic!("couldn't create {}: {}", file_path, why),
+        Ok(file) => file,
+    };
+    let mut header = format!("File, {}", recording::TRIAL_HEADER);
+    if let Some(demographics) = demographics {
+        for column in demographics.covariate_columns() {
+            header.push_str(&format!(", {}", column));
+        }
+    }
+    writeln!(file, "{}", header).expect("Failed to write to file");
+    for (path, trials) in participants {
+        let covariates = demographics.map_or(String::new(), |demographics| {
+            demographics
+                .covariates(&demographics::subject_id(path))
+                .iter()
+                .map(|value| format!(", {}", value))
+                .collect::<String>()
+        });
+        for (index, trial) in trials.iter().enumerate() {
+            writeln!(
+                file,
+                "{}, {}{}",
+                path,
+                recording::trial_row(index + 1, trial),
+                covariates
+            )
+            .expect("Failed to write file.");
+        }
+    }
+}
+
+/// Writes descriptives of every numeric summary column, once per group.
+/// Participants missing from the demographics file are grouped as "NA".
+fn write_group_summary(
+    file_path: &str,
+    header: &[String],
+    rows: &[Vec<String>],
+    groups: &[String],
+) {
+    let mut file = match std::fs::File::create(file_path) {
+        Err(why) => panic!("couldn't create {}: {}", file_path, why),
         Ok(file) => file,
     };
     writeln!(
-        output_file,
-        "{}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}",
-        "File",
-        "All Accuracy (%)",
-        "Angry Male Accuracy (%)",
-        "Happy Male Accuracy (%)",
-        "Neutral Male Accuracy (%)",
-        "Angry Female Accuracy (%)",
-        "Happy Female Accuracy (%)",
-        "Neutral Female Accuracy (%)",
-        "All Reaction Time (ms)",
-        "Angry Male Reaction Time (ms)",
-        "Happy Male Reaction Time (ms)",
-        "Neutral Male Reaction Time (ms)",
-        "Angry Female Reaction Time (ms)",
-        "Happy Female Reaction Time (ms)",
-        "Neutral Female Reaction Time (ms)",
+        file,
+        "Group, Measure, N, Mean, SD, SE, 95% CI Lower, 95% CI Upper"
     )
     .expect("Failed to write to file");
-    for file in std::fs::read_dir(input_directory).unwrap() {
+    let mut group_names = groups.to_vec();
+    group_names.sort();
+    group_names.dedup();
+    for group_name in &group_names {
+        for (column, measure) in header.iter().enumerate().skip(1) {
+            let values = rows
+                .iter()
+                .zip(groups)
+                .filter(|(_, group)| *group == group_name)
+                .filter_map(|(row, _)| row[column].parse::<f64>().ok())
+                .collect::<Vec<_>>();
+            let Some(descriptives) = group::describe(&values) else {
+                continue;
+            };
+            let (lower, upper) = match descriptives.confidence_interval_95 {
+                Some((lower, upper)) => (Some(lower), Some(upper)),
+                None => (None, None),
+            };
+            writeln!(
+                file,
+                "{}, {}, {}, {:.2}, {}, {}, {}, {}",
+                group_name,
+                measure,
+                descriptives.count,
+                descriptives.mean,
+                format_optional(descriptives.standard_deviation),
+                format_optional(descriptives.standard_error),
+                format_optional(lower),
+                format_optional(upper),
+            )
+            .expect("Failed to write file.");
+        }
+    }
+}
+
+fn write_anova(file_path: &str, exclusions: &Exclusions, participants: &[(String, Vec<Trial>)]) {
+    let mut file = match std::fs::File::create(file_path) {
+        Err(why) => panic!("couldn't create {}: {}", file_path, why),
+        Ok(file) => file,
+    };
+    writeln!(
+        file,
+        "Measure, Effect, df, Error df, F, p, GG Epsilon, GG p, Partial Eta Squared"
+    )
+    .expect("Failed to write to file");
+    let cell_means = |measure: &dyn Fn(&[Trial]) -> f64| {
+        participants
+            .iter()
+            .map(|(_, trials)| {
+                anova::CELLS.map(|(condition, sex)| {
+                    measure(&emotional_faces_recode::trials_matching(
+                        trials,
+                        Some(&condition),
+                        Some(&sex),
+                    ))
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+    let accuracy = cell_means(&|trials| accuracy_percentage(&exclusions.accuracy_trials(trials)));
+    let reaction_time = cell_means(&|trials| {
+        emotional_faces_recode::reaction_time_milliseconds(&exclusions.reaction_time_trials(trials))
+            .map_or(f64::NAN, |milliseconds| milliseconds as f64)
+    });
+    for (measure, means) in [
+        ("Accuracy (%)", accuracy),
+        ("Reaction Time (ms)", reaction_time),
+    ] {
+        let Some(effects) = anova::emotion_by_sex_anova(&means) else {
+            println!("Too few complete participants for the {} ANOVA.", measure);
+            continue;
+        };
+        for effect in effects {
+            writeln!(
+                file,
+                "{}, {}, {}, {}, {:.3}, {:.4}, {:.3}, {:.4}, {:.3}",
+                measure,
+                effect.name,
+                effect.effect_degrees_of_freedom,
+                effect.error_degrees_of_freedom,
+                effect.f,
+                effect.p,
+                effect.greenhouse_geisser_epsilon,
+                effect.greenhouse_geisser_p,
+                effect.partial_eta_squared,
+            )
+            .expect("Failed to write file.");
+        }
+    }
+}
+
+fn write_validation_report(input_directory: &str, file_path: &str, decoder: &TriggerDecoder) {
+    let mut file = match std::fs::File::create(file_path) {
+        Err(why) => panic!("couldn't create {}: {}", file_path, why),
+        Ok(file) => file,
+    };
+    let cell_names = anova::CELLS
+        .iter()
+        .map(|(condition, sex)| format!("{:?} {:?} Trials", condition, sex))
+        .collect::<Vec<_>>();
+    writeln!(
+        file,
+        "File, Status, Trials, {}, Unknown Triggers, Trials Without Visual, Response Rate (%), Duplicated Triggers, Problems",
+        cell_names.join(", ")
+    )
+    .expect("Failed to write to file");
+    for entry in std::fs::read_dir(input_directory).unwrap() {
+        let path = entry.unwrap().path();
+        let extension = path.extension().unwrap_or(std::ffi::OsStr::new(""));
+        if extension != "evt" {
+            continue;
+        }
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let report = validation::validate(&contents, decoder);
+        println!("{}: {}", path.to_str().unwrap(), report.status);
+        for problem in &report.problems {
+            println!("    {}", problem);
+        }
+        writeln!(
+            file,
+            "{}, {}, {}, {}, {}, {}, {:.2}, {}, {}",
+            path.to_str().unwrap(),
+            report.status,
+            report.trial_count,
+            report
+                .condition_counts
+                .map(|count| count.to_string())
+                .join(", "),
+            report.unknown_trigger_count,
+            report.trials_without_visual,
+            report.response_rate_percentage,
+            report.duplicated_trigger_count,
+            report.problems.join("; "),
+        )
+        .expect("Failed to write file.");
+    }
+}
+
+fn write_timing_report(
+    input_directory: &str,
+    file_path: &str,
+    reconstruction: &ReconstructionOptions,
+) {
+    let mut file = match std::fs::File::create(file_path) {
+        Err(why) => panic!("couldn't create {}: {}", file_path, why),
+        Ok(file) => file,
+    };
+    writeln!(
+        file,
+        "File, Measure, N, Mean, SD, Min, Median, Max, Lower Fence, Upper Fence, Outliers, Outlier Trials"
+    )
+    .expect("Failed to write to file");
+    for entry in std::fs::read_dir(input_directory).unwrap() {
+        let path = entry.unwrap().path();
+        let extension = path.extension().unwrap_or(std::ffi::OsStr::new(""));
+        if extension != "evt" {
+            continue;
+        }
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let timings = timing::trial_timings(&parse_events(&contents), reconstruction);
+        for measure in TimingMeasure::ALL {
+            let Some(distribution) = timing::timing_distribution(&timings, measure) else {
+                continue;
+            };
+            if !distribution.outlier_trials.is_empty() {
+                println!(
+                    "{}: {} outside {:.2} to {:.2} on trials {:?}",
+                    path.to_str().unwrap(),
+                    measure.name(),
+                    distribution.lower_fence,
+                    distribution.upper_fence,
+                    distribution.outlier_trials,
+                );
+            }
+            writeln!(
+                file,
+                "{}, {}, {}, {:.2}, {}, {:.2}, {:.2}, {:.2}, {:.2}, {:.2}, {}, {}",
+                path.to_str().unwrap(),
+                measure.name(),
+                distribution.count,
+                distribution.mean,
+                format_optional(distribution.standard_deviation),
+                distribution.minimum,
+                distribution.median,
+                distribution.maximum,
+                distribution.lower_fence,
+                distribution.upper_fence,
+                distribution.outlier_trials.len(),
+                distribution
+                    .outlier_trials
+                    .iter()
+                    .map(|trial| trial.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            )
+            .expect("Failed to write file.");
+        }
+    }
+}
+
+fn print_decoded_triggers(file_path: &str, decoder: &TriggerDecoder) {
+    let contents = match std::fs::read_to_string(file_path) {
+        Err(why) => panic!("couldn't read {}: {}", file_path, why),
+        Ok(contents) => contents,
+    };
+    println!("Decoding with {}", decoder.description());
+    println!("Time (us)\tCode\tTrial\tLines");
+    for event in triggers::decode_events(&parse_events(&contents), decoder) {
+        println!(
+            "{}\t{}\t{}\t{}",
+            event.time_microseconds,
+            event.trigger_code,
+            event
+                .trial
+                .map_or("-".to_string(), |trial| trial.to_string()),
+            event.decoded,
+        );
+    }
+}
+
+fn write_simulation(args: &[String]) {
+    let mut positional = Vec::new();
+    let mut simulation = Simulation::default();
+    let mut trial_output_file_path = None;
+    let mut arguments = args.iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--trials" => simulation.trials = option_value(&mut arguments, argument),
+            "--blocks" => simulation.blocks = option_value(&mut arguments, argument),
+            "--order" => simulation.order = option_value(&mut arguments, argument),
+            "--rt" => {
+                option_value::<CellSetting<ReactionTimeDistribution>>(&mut arguments, argument)
+                    .apply(&mut simulation.cells, |cell, reaction_time| {
+                        cell.reaction_time = reaction_time
+                    })
+            }
+            "--error-rate" => option_value::<CellSetting<f64>>(&mut arguments, argument)
+                .apply(&mut simulation.cells, |cell, error_rate| {
+                    cell.error_rate = error_rate
+                }),
+            "--duplicate-triggers" => {
+                simulation.glitches.duplicate_triggers = option_value(&mut arguments, argument)
+            }
+            "--lone-visuals" => {
+                simulation.glitches.lone_visuals = option_value(&mut arguments, argument)
+            }
+            "--missing-responses" => {
+                simulation.glitches.missing_responses = option_value(&mut arguments, argument)
+            }
+            "--both-buttons" => {
+                simulation.glitches.both_buttons = option_value(&mut arguments, argument)
+            }
+            "--seed" => simulation.seed = option_value(&mut arguments, argument),
+            "--trial-output" => {
+                trial_output_file_path = Some(option_value::<String>(&mut arguments, argument))
+            }
+            option if option.starts_with("--") => panic!("unknown option {}\n{}", option, USAGE),
+            _ => positional.push(argument.clone()),
+        }
+    }
+    if positional.len() != 1 || simulation.trials == 0 {
+        panic!("{}", USAGE);
+    }
+    if let Err(why) = simulation.check() {
+        panic!("{}\n{}", why, USAGE);
+    }
+    let file_path = &positional[0];
+    let recording = simulation.generate();
+    std::fs::write(file_path, &recording.contents)
+        .unwrap_or_else(|why| panic!("couldn't write {}: {}", file_path, why));
+    if let Some(trial_output_file_path) = trial_output_file_path {
+        write_trials(
+            &trial_output_file_path,
+            &[(file_path.clone(), recording.trials)],
+            None,
+        );
+    }
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.get(1).is_some_and(|command| command == "simulate") {
+        write_simulation(&args[2..]);
+        return;
+    }
+    if let Some(command @ ("validate" | "timing" | "inspect")) =
+        args.get(1).map(|command| command.as_str())
+    {
+        let mut positional = Vec::new();
+        let mut reconstruction = ReconstructionOptions::default();
+        let mut arguments = args.iter().skip(2);
+        while let Some(argument) = arguments.next() {
+            match argument.as_str() {
+                "--trigger-lines" => {
+                    reconstruction.decoder = option_value(&mut arguments, argument)
+                }
+                "--display-lag" if command == "timing" => {
+                    reconstruction.display_lag_microseconds =
+                        option_value::<i64>(&mut arguments, argument) * 1000
+                }
+                option if option.starts_with("--") => {
+                    panic!("unknown option {}\n{}", option, USAGE)
+                }
+                _ => positional.push(argument.as_str()),
+            }
+        }
+        match (command, positional.as_slice()) {
+            ("validate", [input_directory, file_path]) => {
+                write_validation_report(input_directory, file_path, &reconstruction.decoder)
+            }
+            ("timing", [input_directory, file_path]) => {
+                write_timing_report(input_directory, file_path, &reconstruction)
+            }
+            ("inspect", [file_path]) => print_decoded_triggers(file_path, &reconstruction.decoder),
+            _ => panic!("{}", USAGE),
+        }
+        return;
+    }
+    let options = parse_options(&args);
+    let demographics = options.demographics_file_path.as_ref().map(|file_path| {
+        match std::fs::read_to_string(file_path) {
+            Err(why) => panic!("couldn't read {}: {}", file_path, why),
+            Ok(contents) => demographics::parse_demographics(&contents),
+        }
+    });
+    let mapping_sources = MappingSources {
+        swapped_subjects: options.swapped_subjects.clone(),
+        command_line: options.mapping,
+        table: options
+            .mapping_table_file_path
+            .as_ref()
+            .map_or(Vec::new(), |file_path| {
+                match std::fs::read_to_string(file_path) {
+                    Err(why) => panic!("couldn't read {}: {}", file_path, why),
+                    Ok(contents) => response_mapping::parse_mapping_table(&contents)
+                        .unwrap_or_else(|why| panic!("{}: {}", file_path, why)),
+                }
+            }),
+        swap_rule: options.swap_rule.clone().zip(demographics.clone()),
+    };
+    let mut participants = Vec::new();
+    let mut mappings = Vec::new();
+    for file in std::fs::read_dir(&options.input_directory).unwrap() {
         let path = file.unwrap().path();
         let extension = path.extension().unwrap_or(std::ffi::OsStr::new(""));
         if extension != "evt" {
@@ -55,32 +1176,162 @@ fn main() {
         }
         println!("Analyzing {}", path.to_str().unwrap());
         let contents = std::fs::read_to_string(&path).unwrap();
-        let events = parse_events(&contents);
-        let trials = reconstruct_trials(events);
-        if trials.len() != 240 {
-            println!("Unexpected number of trials: {}", trials.len());
+        let subject = demographics::subject_id(path.to_str().unwrap());
+        let recording = recording::analyze_recording(
+            &subject,
+            &contents,
+            &mapping_sources,
+            &options.reconstruction,
+            options.fix_swapped,
+        );
+        if recording.resolved_mapping == ResponseMapping::Swapped {
+            println!(
+                "Scoring with swapped buttons (from the {}).",
+                recording.resolved_source
+            );
+        }
+        if recording.trials.len() != 240 {
+            println!("Unexpected number of trials: {}", recording.trials.len());
             println!("Skipping.");
             continue;
         }
+        if let Some(detection) = &recording.swap_detection {
+            println!(
+                "WARNING: {} looks like it was recorded with the buttons swapped: {:.2}% of \
+                 answered trials correct with the {} mapping, {:.2}% with the {} mapping.",
+                path.to_str().unwrap(),
+                detection.resolved_accuracy_percentage,
+                recording.resolved_mapping,
+                detection.swapped_accuracy_percentage,
+                recording.resolved_mapping.swapped(),
+            );
+            if recording.rescored {
+                println!(
+                    "Re-scoring with the {} mapping.",
+                    recording.resolved_mapping.swapped()
+                );
+            } else {
+                println!("Pass --fix-swapped to re-score it.");
+            }
+        }
+        if options.ez_diffusion {
+            for cell in &CELLS {
+                let cell_trials = trials_matching(&recording.trials, cell);
+                if let Err(why) = ez_diffusion(
+                    &options.exclusions.accuracy_trials(&cell_trials),
+                    &options.exclusions.reaction_time_trials(&cell_trials),
+                ) {
+                    println!(
+                        "WARNING: no EZ-diffusion estimates for {}: {}.",
+                        cell.0,
+                        why.name()
+                    );
+                }
+            }
+        }
+        let (mapping, source) = recording.mapping();
+        mappings.push((mapping, source, recording.swap_detection.is_some()));
+        participants.push((path.to_str().unwrap().to_string(), recording.trials));
+    }
+    if let Some(demographics) = &demographics {
+        let subjects = participants
+            .iter()
+            .map(|(path, _)| demographics::subject_id(path))
+            .collect::<Vec<_>>();
+        for subject in &subjects {
+            if demographics.row(subject).is_none() {
+                println!("No demographics for {}.", subject);
+            }
+        }
+        for subject in demographics.unmatched_subjects(&subjects) {
+            println!("No recording for {} in the demographics file.", subject);
+        }
+    }
+    if let Some(file_path) = &options.trial_output_file_path {
+        write_trials(file_path, &participants, demographics.as_ref());
+    }
+    let measures = measures(&options, &participants);
+    let mut output_file = match std::fs::File::create(&options.output_file_path) {
+        Err(why) => panic!("couldn't create {}: {}", options.output_file_path, why),
+        Ok(file) => file,
+    };
+    let mut header = vec!["File".to_string()];
+    for measure in &measures {
+        for (cell_name, _, _) in measure.cells {
+            for name in &measure.names {
+                header.push(if cell_name.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{} {}", cell_name, name)
+                });
+            }
+        }
+    }
+    // A file that looks swapped is always recorded, re-scored or not.
+    let records_mapping =
+        options.records_mapping() || mappings.iter().any(|(_, _, looks_swapped)| *looks_swapped);
+    let mapping_columns = if records_mapping {
+        vec![
+            "Response Mapping".to_string(),
+            "Mapping Source".to_string(),
+            "Looks Swapped".to_string(),
+        ]
+    } else {
+        Vec::new()
+    };
+    let covariate_columns = demographics
+        .as_ref()
+        .map_or(&[][..], |demographics| demographics.covariate_columns());
+    writeln!(
+        output_file,
+        "{}",
+        [&header[..], &mapping_columns, covariate_columns]
+            .concat()
+            .join(", ")
+    )
+    .expect("Failed to write to file");
+    let mut rows = Vec::new();
+    for ((path, trials), (mapping, source, looks_swapped)) in participants.iter().zip(&mappings) {
+        let mut row = vec![path.clone()];
+        for measure in &measures {
+            for cell in measure.cells {
+                row.extend((measure.value)(&trials_matching(trials, cell)));
+            }
+        }
+        let covariates = demographics.as_ref().map_or(Vec::new(), |demographics| {
+            demographics.covariates(&demographics::subject_id(path))
+        });
+        let mapping = if records_mapping {
+            vec![
+                mapping.to_string(),
+                source.to_string(),
+                looks_swapped.to_string(),
+            ]
+        } else {
+            Vec::new()
+        };
         writeln!(
             output_file,
-            "{}, {:.2}, {:.2}, {:.2}, {:.2}, {:.2}, {:.2}, {:.2}, {}, {}, {}, {}, {}, {}, {}",
-            path.to_str().unwrap(),
-            accuracy_percentage(&trials),
-            accuracy_percentage(&trials_matching(&trials, Condition::Angry, Sex::Male)),
-            accuracy_percentage(&trials_matching(&trials, Condition::Happy, Sex::Male)),
-            accuracy_percentage(&trials_matching(&trials, Condition::Neutral, Sex::Male)),
-            accuracy_percentage(&trials_matching(&trials, Condition::Angry, Sex::Female)),
-            accuracy_percentage(&trials_matching(&trials, Condition::Happy, Sex::Female)),
-            accuracy_percentage(&trials_matching(&trials, Condition::Neutral, Sex::Female)),
-            reaction_time_milliseconds(&trials),
-            reaction_time_milliseconds(&trials_matching(&trials, Condition::Angry, Sex::Male)),
-            reaction_time_milliseconds(&trials_matching(&trials, Condition::Happy, Sex::Male)),
-            reaction_time_milliseconds(&trials_matching(&trials, Condition::Neutral, Sex::Male)),
-            reaction_time_milliseconds(&trials_matching(&trials, Condition::Angry, Sex::Female)),
-            reaction_time_milliseconds(&trials_matching(&trials, Condition::Happy, Sex::Female)),
-            reaction_time_milliseconds(&trials_matching(&trials, Condition::Neutral, Sex::Female)),
+            "{}",
+            [&row[..], &mapping, &covariates].concat().join(", ")
         )
         .expect("Failed to write file.");
+        rows.push(row);
+    }
+    if let Some(file_path) = &options.group_output_file_path {
+        let groups = participants
+            .iter()
+            .map(|(path, _)| match (&demographics, &options.group_by) {
+                (Some(demographics), Some(column)) => demographics
+                    .value(&demographics::subject_id(path), column)
+                    .unwrap_or("NA")
+                    .to_string(),
+                _ => "All".to_string(),
+            })
+            .collect::<Vec<_>>();
+        write_group_summary(file_path, &header, &rows, &groups);
+    }
+    if let Some(file_path) = &options.anova_output_file_path {
+        write_anova(file_path, &options.exclusions, &participants);
     }
 }
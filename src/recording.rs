@@ -0,0 +1,189 @@
+use crate::response_mapping::{self, MappingSource, MappingSources};
+use crate::{
+    artifacts, parse_events, reconstruct_trials_with_options, ReconstructionOptions,
+    ResponseMapping, Trial,
+};
+
+/// The trial output columns after the file name.
+pub const TRIAL_HEADER: &str = "Trial, Block, Condition, Sex, Correct, Reaction Time (ms), Stimulus Time (us), Button Press Time (us), Artifact, Onset Reference, Extra Presses, Both Buttons";
+
+/// Answered accuracy of a recording `looks_swapped` found to be the wrong
+/// way round.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SwapDetection {
+    pub resolved_accuracy_percentage: f64,
+    pub swapped_accuracy_percentage: f64,
+}
+
+/// One .evt file, scored.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Recording {
+    pub trials: Vec<Trial>,
+    /// What the mapping sources gave for the file.
+    pub resolved_mapping: ResponseMapping,
+    pub resolved_source: MappingSource,
+    pub swap_detection: Option<SwapDetection>,
+    /// Whether the trials were scored with the resolved mapping swapped,
+    /// after it looked swapped.
+    pub rescored: bool,
+}
+
+impl Recording {
+    /// The mapping the trials were scored with, and where it came from.
+    pub fn mapping(&self) -> (ResponseMapping, MappingSource) {
+        if self.rescored {
+            (self.resolved_mapping.swapped(), MappingSource::Detected)
+        } else {
+            (self.resolved_mapping, self.resolved_source)
+        }
+    }
+}
+
+/// Scores the contents of a .evt file as the command line tool does: with
+/// the mapping `mapping_sources` give for the subject, checked against the
+/// swapped mapping and re-scored with it if `fix_swapped`, and with trials
+/// overlapping an artifact segment flagged.
+pub fn analyze_recording(
+    subject: &str,
+    contents: &str,
+    mapping_sources: &MappingSources,
+    options: &ReconstructionOptions,
+    fix_swapped: bool,
+) -> Recording {
+    let (resolved_mapping, resolved_source) = mapping_sources.resolve(subject, contents);
+    let reconstruction = ReconstructionOptions {
+        mapping: resolved_mapping,
+        ..options.clone()
+    };
+    let mut trials = reconstruct_trials_with_options(parse_events(contents), &reconstruction);
+    let swapped_trials = reconstruct_trials_with_options(
+        parse_events(contents),
+        &ReconstructionOptions {
+            mapping: resolved_mapping.swapped(),
+            ..reconstruction
+        },
+    );
+    let swap_detection =
+        response_mapping::looks_swapped(&trials, &swapped_trials).then(|| SwapDetection {
+            resolved_accuracy_percentage: response_mapping::answered_accuracy_percentage(&trials)
+                .unwrap(),
+            swapped_accuracy_percentage: response_mapping::answered_accuracy_percentage(
+                &swapped_trials,
+            )
+            .unwrap(),
+        });
+    let rescored = fix_swapped && swap_detection.is_some();
+    if rescored {
+        trials = swapped_trials;
+    }
+    artifacts::flag_artifacts(&mut trials, &artifacts::parse_artifact_segments(contents));
+    Recording {
+        trials,
+        resolved_mapping,
+        resolved_source,
+        swap_detection,
+        rescored,
+    }
+}
+
+/// One row of the trial output, after the file name, for the 1-based trial
+/// `number`.
+pub fn trial_row(number: usize, trial: &Trial) -> String {
+    format!(
+        "{}, {}, {:?}, {:?}, {}, {}, {}, {}, {}, {}, {}, {}",
+        number,
+        trial.block,
+        trial.condition,
+        trial.sex,
+        trial.correct_response,
+        trial
+            .response_time_milliseconds
+            .map_or("NaN".to_string(), |milliseconds| milliseconds.to_string()),
+        trial.stimulus_time_microseconds,
+        trial
+            .button_press_time_microseconds
+            .map_or("NaN".to_string(), |time| time.to_string()),
+        trial.artifact,
+        trial.onset_reference,
+        trial.extra_presses,
+        trial.both_buttons,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::response_mapping::{MappingSource, MappingSources};
+    use crate::simulation::Simulation;
+    use crate::{ReconstructionOptions, ResponseMapping};
+
+    /// A recording answered almost always with the wrong button.
+    fn swapped_contents() -> String {
+        let mut simulation = Simulation::default();
+        for cell in simulation.cells.iter_mut() {
+            cell.error_rate = 0.9;
+        }
+        simulation.generate().contents
+    }
+
+    #[test]
+    fn detects_without_rescoring() {
+        let recording = super::analyze_recording(
+            "p01",
+            &swapped_contents(),
+            &MappingSources::default(),
+            &ReconstructionOptions::default(),
+            false,
+        );
+        assert!(recording.swap_detection.is_some());
+        assert!(!recording.rescored);
+        assert_eq!(
+            (ResponseMapping::Standard, MappingSource::Default),
+            recording.mapping()
+        );
+    }
+
+    #[test]
+    fn rescores_with_fix_swapped() {
+        let contents = swapped_contents();
+        let recording = super::analyze_recording(
+            "p01",
+            &contents,
+            &MappingSources::default(),
+            &ReconstructionOptions::default(),
+            true,
+        );
+        let detection = recording.swap_detection.as_ref().unwrap();
+        assert!(detection.resolved_accuracy_percentage < 20.);
+        assert!(detection.swapped_accuracy_percentage > 80.);
+        assert_eq!(
+            (ResponseMapping::Swapped, MappingSource::Detected),
+            recording.mapping()
+        );
+        let correct = recording
+            .trials
+            .iter()
+            .filter(|trial| trial.correct_response)
+            .count();
+        assert!(correct > recording.trials.len() / 2);
+    }
+
+    #[test]
+    fn trial_row() {
+        let trial = crate::Trial {
+            correct_response: true,
+            condition: crate::Condition::Angry,
+            response_time_milliseconds: Some(512),
+            stimulus_time_microseconds: 5016000,
+            button_press_time_microseconds: Some(5528000),
+            ..Default::default()
+        };
+        assert_eq!(
+            "3, 1, Angry, Male, true, 512, 5016000, 5528000, false, photodiode, 0, false",
+            super::trial_row(3, &trial)
+        );
+        assert_eq!(
+            super::TRIAL_HEADER.split(", ").count(),
+            super::trial_row(3, &trial).split(", ").count()
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use crate::demographics::{self, Demographics, SwapRule};
+use crate::{ResponseMapping, Trial};
+
+// A recording is taken to have swapped buttons when fewer of its answered
+// trials than this are correct under the mapping it was scored with...
+const SWAPPED_BELOW_PERCENT: f64 = 25.;
+// ...and at least this many are correct with the buttons swapped.
+const NORMAL_FROM_PERCENT: f64 = 70.;
+
+impl ResponseMapping {
+    pub fn swapped(self) -> ResponseMapping {
+        match self {
+            ResponseMapping::Standard => ResponseMapping::Swapped,
+            ResponseMapping::Swapped => ResponseMapping::Standard,
+        }
+    }
+}
+
+impl std::str::FromStr for ResponseMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "standard" => Ok(ResponseMapping::Standard),
+            "swapped" => Ok(ResponseMapping::Swapped),
+            _ => Err(format!("unknown response mapping: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for ResponseMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResponseMapping::Standard => write!(f, "standard"),
+            ResponseMapping::Swapped => write!(f, "swapped"),
+        }
+    }
+}
+
+/// The response mapping the command line gives every file.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum MappingOverride {
+    /// Each file's mapping comes from the other sources.
+    #[default]
+    Auto,
+    Forced(ResponseMapping),
+}
+
+impl std::str::FromStr for MappingOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            Ok(MappingOverride::Auto)
+        } else {
+            s.parse().map(MappingOverride::Forced)
+        }
+    }
+}
+
+/// Where a file's response mapping came from, in order of precedence.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MappingSource {
+    CommandLine,
+    Table,
+    Demographics,
+    Marker,
+    Default,
+    /// Re-scored after `looks_swapped` found the mapping from any of the
+    /// other sources to be the wrong way round.
+    Detected,
+}
+
+impl std::fmt::Display for MappingSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MappingSource::CommandLine => write!(f, "command line"),
+            MappingSource::Table => write!(f, "mapping table"),
+            MappingSource::Demographics => write!(f, "demographics"),
+            MappingSource::Marker => write!(f, ".evt marker"),
+            MappingSource::Default => write!(f, "default"),
+            MappingSource::Detected => write!(f, "detected"),
+        }
+    }
+}
+
+// Comment text that marks a recording's response mapping, followed by
+// "standard" or "swapped".
+const MARKER: &str = "response mapping:";
+
+/// Reads a "Response mapping: swapped" (or "standard") comment from a BESA
+/// .evt file, if there is one.
+pub fn parse_mapping_marker(input: &str) -> Option<ResponseMapping> {
+    input.lines().find_map(|line| {
+        let lowercase = line.to_ascii_lowercase();
+        let start = lowercase.find(MARKER)? + MARKER.len();
+        lowercase[start..].split_whitespace().next()?.parse().ok()
+    })
+}
+
+/// Reads a CSV of subject IDs in the first column and their mapping in the
+/// second, with a header row.
+pub fn parse_mapping_table(input: &str) -> Result<Vec<(String, ResponseMapping)>, String> {
+    demographics::parse_demographics(input)
+        .rows
+        .iter()
+        .map(|row| match (row.first(), row.get(1)) {
+            (Some(subject), Some(mapping)) => Ok((subject.clone(), mapping.parse()?)),
+            _ => Err(format!(
+                "expected a subject ID and a mapping: {}",
+                row.join(", ")
+            )),
+        })
+        .collect::<Result<Vec<_>, _>>()
+}
+
+/// Accuracy over the trials with a button press, so missed responses count
+/// against neither mapping.
+pub fn answered_accuracy_percentage(trials: &[Trial]) -> Option<f64> {
+    let answered = trials
+        .iter()
+        .filter(|trial| trial.button_press_time_microseconds.is_some())
+        .cloned()
+        .collect::<Vec<_>>();
+    if answered.is_empty() {
+        None
+    } else {
+        Some(crate::accuracy_percentage(&answered))
+    }
+}
+
+/// Whether a recording scored as `configured` is far below chance while
+/// the same recording scored with the buttons the other way round is not.
+pub fn looks_swapped(configured: &[Trial], swapped: &[Trial]) -> bool {
+    match (
+        answered_accuracy_percentage(configured),
+        answered_accuracy_percentage(swapped),
+    ) {
+        (Some(configured), Some(swapped)) => {
+            configured < SWAPPED_BELOW_PERCENT && swapped >= NORMAL_FROM_PERCENT
+        }
+        _ => false,
+    }
+}
+
+/// Every way a participant's mapping can be given. Files nothing applies to
+/// are scored with the standard mapping.
+#[derive(Debug, Default, Clone)]
+pub struct MappingSources {
+    pub swapped_subjects: Vec<String>,
+    /// For every file but the `swapped_subjects`.
+    pub command_line: MappingOverride,
+    pub table: Vec<(String, ResponseMapping)>,
+    pub swap_rule: Option<(SwapRule, Demographics)>,
+}
+
+impl MappingSources {
+    /// `contents` is the .evt file, searched for a marker.
+    pub fn resolve(&self, subject: &str, contents: &str) -> (ResponseMapping, MappingSource) {
+        if self
+            .swapped_subjects
+            .iter()
+            .any(|swapped| swapped == subject)
+        {
+            return (ResponseMapping::Swapped, MappingSource::CommandLine);
+        }
+        if let MappingOverride::Forced(mapping) = self.command_line {
+            return (mapping, MappingSource::CommandLine);
+        }
+        if let Some((_, mapping)) = self.table.iter().find(|(id, _)| id == subject) {
+            return (*mapping, MappingSource::Table);
+        }
+        if let Some((rule, demographics)) = &self.swap_rule {
+            if demographics.row(subject).is_some() {
+                let mapping = if rule.swaps(demographics, subject) {
+                    ResponseMapping::Swapped
+                } else {
+                    ResponseMapping::Standard
+                };
+                return (mapping, MappingSource::Demographics);
+            }
+        }
+        match parse_mapping_marker(contents) {
+            Some(mapping) => (mapping, MappingSource::Marker),
+            None => (ResponseMapping::Standard, MappingSource::Default),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MappingOverride, MappingSource, MappingSources};
+    use crate::{Condition, ResponseMapping, Sex, Trial};
+
+    const MARKED: &str = "Tmu         	Code	TriNo	Comnt	Ver-C
+3809479        	11	0	all  6017 0.874 2.19
+3900000        	11	0	Response mapping: Swapped
+4618000        	1	4096	FIFF Trigger: 4096";
+
+    #[test]
+    fn parse_mapping_marker() {
+        assert_eq!(
+            Some(ResponseMapping::Swapped),
+            super::parse_mapping_marker(MARKED)
+        );
+        assert_eq!(
+            None,
+            super::parse_mapping_marker("4618000        	1	4096	FIFF Trigger: 4096")
+        );
+    }
+
+    fn trial(correct_response: bool, pressed: bool) -> Trial {
+        Trial {
+            correct_response,
+            condition: Condition::Angry,
+            sex: Sex::Male,
+            response_time_milliseconds: if correct_response { Some(600) } else { None },
+            button_press_time_microseconds: if pressed { Some(600_000) } else { None },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn looks_swapped() {
+        let mostly_wrong = [
+            vec![trial(false, true); 18],
+            vec![trial(true, true); 2],
+            vec![trial(false, false); 20],
+        ]
+        .concat();
+        let mostly_right = [
+            vec![trial(true, true); 18],
+            vec![trial(false, true); 2],
+            vec![trial(false, false); 20],
+        ]
+        .concat();
+        assert_eq!(
+            Some(10.),
+            super::answered_accuracy_percentage(&mostly_wrong)
+        );
+        assert!(super::looks_swapped(&mostly_wrong, &mostly_right));
+        assert!(!super::looks_swapped(&mostly_right, &mostly_wrong));
+        // Near chance either way is a poor performer, not a swapped mapping.
+        let chance = [vec![trial(true, true); 10], vec![trial(false, true); 10]].concat();
+        assert!(!super::looks_swapped(&chance, &chance));
+        assert!(!super::looks_swapped(&[], &[]));
+    }
+
+    #[test]
+    fn parse_mapping_table() {
+        assert_eq!(
+            Ok(vec![
+                ("p01".to_string(), ResponseMapping::Standard),
+                ("p02".to_string(), ResponseMapping::Swapped)
+            ]),
+            super::parse_mapping_table("Subject, Mapping\np01, standard\np02, swapped\n")
+        );
+        assert!(super::parse_mapping_table("Subject, Mapping\np01, left\n").is_err());
+    }
+
+    #[test]
+    fn parse_mapping_override() {
+        assert_eq!(Ok(MappingOverride::Auto), "Auto".parse());
+        assert_eq!(
+            Ok(MappingOverride::Forced(ResponseMapping::Standard)),
+            "standard".parse()
+        );
+        assert!("left".parse::<MappingOverride>().is_err());
+    }
+
+    #[test]
+    fn resolve_in_order_of_precedence() {
+        let mut sources = MappingSources {
+            swapped_subjects: vec!["p01".to_string()],
+            command_line: MappingOverride::Auto,
+            table: vec![
+                ("p01".to_string(), ResponseMapping::Standard),
+                ("p02".to_string(), ResponseMapping::Standard),
+            ],
+            swap_rule: Some((
+                "Handedness=left".parse().unwrap(),
+                crate::demographics::parse_demographics(
+                    "Subject, Handedness\np02, left\np03, left\np04, right\n",
+                ),
+            )),
+        };
+        assert_eq!(
+            (ResponseMapping::Swapped, MappingSource::CommandLine),
+            sources.resolve("p01", "")
+        );
+        assert_eq!(
+            (ResponseMapping::Standard, MappingSource::Table),
+            sources.resolve("p02", MARKED)
+        );
+        assert_eq!(
+            (ResponseMapping::Swapped, MappingSource::Demographics),
+            sources.resolve("p03", "")
+        );
+        assert_eq!(
+            (ResponseMapping::Standard, MappingSource::Demographics),
+            sources.resolve("p04", MARKED)
+        );
+        assert_eq!(
+            (ResponseMapping::Swapped, MappingSource::Marker),
+            sources.resolve("p05", MARKED)
+        );
+        assert_eq!(
+            (ResponseMapping::Standard, MappingSource::Default),
+            sources.resolve("p05", "")
+        );
+        sources.command_line = MappingOverride::Forced(ResponseMapping::Standard);
+        assert_eq!(
+            (ResponseMapping::Swapped, MappingSource::CommandLine),
+            sources.resolve("p01", "")
+        );
+        assert_eq!(
+            (ResponseMapping::Standard, MappingSource::CommandLine),
+            sources.resolve("p05", MARKED)
+        );
+    }
+}
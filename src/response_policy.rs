@@ -0,0 +1,132 @@
+use crate::ResponsePolicy;
+
+impl std::str::FromStr for ResponsePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "first" => Ok(ResponsePolicy::First),
+            "last" => Ok(ResponsePolicy::Last),
+            "any-wrong" => Ok(ResponsePolicy::AnyWrong),
+            _ => Err(format!("unknown response policy: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for ResponsePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResponsePolicy::First => write!(f, "first"),
+            ResponsePolicy::Last => write!(f, "last"),
+            ResponsePolicy::AnyWrong => write!(f, "any-wrong"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{reconstruct_trials_with_options, Event, ReconstructionOptions, ResponsePolicy};
+
+    fn event(time_microseconds: i64, trigger_code: i32) -> Event {
+        Event {
+            time_microseconds,
+            trigger_code,
+        }
+    }
+
+    /// A female face answered wrong, then right, then wrong again.
+    fn vacillating() -> Vec<Event> {
+        vec![
+            event(730987008, 23),
+            event(730995008, 4119),
+            event(731918016, 256),
+            event(732334016, 512),
+            event(732572032, 256),
+            event(735000000, 22),
+            event(735010000, 4118),
+            event(735600000, 512),
+        ]
+    }
+
+    fn scored(policy: ResponsePolicy) -> Vec<(bool, Option<i64>, usize)> {
+        reconstruct_trials_with_options(
+            vacillating(),
+            &ReconstructionOptions {
+                response_policy: policy,
+                ..Default::default()
+            },
+        )
+        .iter()
+        .map(|trial| {
+            (
+                trial.correct_response,
+                trial.button_press_time_microseconds,
+                trial.extra_presses,
+            )
+        })
+        .collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Ok(ResponsePolicy::AnyWrong), "Any-Wrong".parse());
+        assert_eq!("last", ResponsePolicy::Last.to_string());
+        assert!("second".parse::<ResponsePolicy>().is_err());
+    }
+
+    #[test]
+    fn first_press() {
+        assert_eq!(
+            vec![(false, Some(731918016), 2), (true, Some(735600000), 0)],
+            scored(ResponsePolicy::First)
+        );
+    }
+
+    #[test]
+    fn last_press() {
+        assert_eq!(
+            vec![(false, Some(732572032), 2), (true, Some(735600000), 0)],
+            scored(ResponsePolicy::Last)
+        );
+        let trials = reconstruct_trials_with_options(
+            vacillating().into_iter().take(4).collect(),
+            &ReconstructionOptions {
+                response_policy: ResponsePolicy::Last,
+                ..Default::default()
+            },
+        );
+        assert!(trials[0].correct_response);
+        assert_eq!(Some(1339), trials[0].response_time_milliseconds);
+    }
+
+    #[test]
+    fn any_wrong_press() {
+        // Right first, then wrong.
+        let right_then_wrong = || {
+            vec![
+                event(730987008, 23),
+                event(730995008, 4119),
+                event(731918016, 512),
+                event(732334016, 256),
+            ]
+        };
+        let first =
+            reconstruct_trials_with_options(right_then_wrong(), &ReconstructionOptions::default());
+        assert!(first[0].correct_response);
+        let any_wrong = reconstruct_trials_with_options(
+            right_then_wrong(),
+            &ReconstructionOptions {
+                response_policy: ResponsePolicy::AnyWrong,
+                ..Default::default()
+            },
+        );
+        assert!(!any_wrong[0].correct_response);
+        assert_eq!(None, any_wrong[0].response_time_milliseconds);
+        assert_eq!(Some(731918016), any_wrong[0].button_press_time_microseconds);
+        assert_eq!(1, any_wrong[0].extra_presses);
+        assert_eq!(
+            vec![(false, Some(731918016), 2), (true, Some(735600000), 0)],
+            scored(ResponsePolicy::AnyWrong)
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use crate::Trial;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ResponseTiming {
+    Anticipatory,
+    InWindow,
+    Late,
+}
+
+/// The range of button press latencies accepted as genuine responses. Either
+/// bound may be left open.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ResponseWindow {
+    pub minimum_milliseconds: Option<i64>,
+    pub maximum_milliseconds: Option<i64>,
+}
+
+impl ResponseWindow {
+    /// Trials without a button press are never anticipatory or late.
+    pub fn timing(&self, trial: &Trial) -> ResponseTiming {
+        match trial.button_press_latency_milliseconds() {
+            Some(latency)
+                if self
+                    .minimum_milliseconds
+                    .is_some_and(|minimum| latency < minimum) =>
+            {
+                ResponseTiming::Anticipatory
+            }
+            Some(latency)
+                if self
+                    .maximum_milliseconds
+                    .is_some_and(|maximum| latency > maximum) =>
+            {
+                ResponseTiming::Late
+            }
+            _ => ResponseTiming::InWindow,
+        }
+    }
+
+    pub fn count(&self, trials: &[Trial], timing: ResponseTiming) -> usize {
+        trials
+            .iter()
+            .filter(|trial| self.timing(trial) == timing)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ResponseTiming, ResponseWindow};
+    use crate::{Condition, Sex, Trial};
+
+    fn trial(button_press_time_microseconds: Option<i64>) -> Trial {
+        Trial {
+            correct_response: true,
+            condition: Condition::Neutral,
+            sex: Sex::Male,
+            response_time_milliseconds: None,
+            stimulus_time_microseconds: 1_000_000,
+            button_press_time_microseconds,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn timing() {
+        let window = ResponseWindow {
+            minimum_milliseconds: Some(150),
+            maximum_milliseconds: Some(2000),
+        };
+        assert_eq!(
+            vec![
+                ResponseTiming::Anticipatory,
+                ResponseTiming::InWindow,
+                ResponseTiming::InWindow,
+                ResponseTiming::Late,
+                ResponseTiming::InWindow
+            ],
+            [
+                Some(1_080_000),
+                Some(1_150_000),
+                Some(3_000_000),
+                Some(3_001_000),
+                None
+            ]
+            .into_iter()
+            .map(|time| window.timing(&trial(time)))
+            .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn open_window() {
+        let window = ResponseWindow::default();
+        assert_eq!(
+            2,
+            window.count(
+                &[trial(Some(1_001_000)), trial(Some(60_000_000))],
+                ResponseTiming::InWindow
+            )
+        );
+    }
+}
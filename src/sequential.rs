@@ -0,0 +1,134 @@
+use crate::Trial;
+
+/// A comparison between trials based on what happened on the trial before.
+/// Each effect is reported as the first group minus the second.
+#[derive(Debug, PartialEq, Clone)]
+pub enum SequentialEffect {
+    /// Trials after an error (including a missed response) against trials
+    /// after a correct response.
+    PostErrorSlowing,
+    /// Trials showing a different emotion than the trial before against
+    /// trials repeating it.
+    EmotionSwitchCost,
+    /// Trials showing a different face sex than the trial before against
+    /// trials repeating it.
+    SexSwitchCost,
+}
+
+impl SequentialEffect {
+    pub const ALL: [SequentialEffect; 3] = [
+        SequentialEffect::PostErrorSlowing,
+        SequentialEffect::EmotionSwitchCost,
+        SequentialEffect::SexSwitchCost,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SequentialEffect::PostErrorSlowing => "Post-Error Slowing",
+            SequentialEffect::EmotionSwitchCost => "Emotion Switch Cost",
+            SequentialEffect::SexSwitchCost => "Sex Switch Cost",
+        }
+    }
+
+    /// Splits the trials of a session, in order, into the effect group and
+    /// the baseline group. The first trial of each block has no trial before
+    /// it and is left out of both.
+    pub fn split(&self, trials: &[Trial]) -> (Vec<Trial>, Vec<Trial>) {
+        let (effect, baseline): (Vec<_>, Vec<_>) = trials
+            .windows(2)
+            .filter(|pair| pair[0].block == pair[1].block)
+            .partition(|pair| {
+                let (previous, current) = (&pair[0], &pair[1]);
+                match self {
+                    SequentialEffect::PostErrorSlowing => !previous.correct_response,
+                    SequentialEffect::EmotionSwitchCost => previous.condition != current.condition,
+                    SequentialEffect::SexSwitchCost => previous.sex != current.sex,
+                }
+            });
+        (
+            effect.into_iter().map(|pair| pair[1].clone()).collect(),
+            baseline.into_iter().map(|pair| pair[1].clone()).collect(),
+        )
+    }
+
+    /// Difference between the two groups of `measure`, `None` if either group
+    /// has no value.
+    pub fn difference(
+        &self,
+        trials: &[Trial],
+        measure: impl Fn(&[Trial]) -> Option<f64>,
+    ) -> Option<f64> {
+        let (effect, baseline) = self.split(trials);
+        Some(measure(&effect)? - measure(&baseline)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SequentialEffect;
+    use crate::{Condition, Sex, Trial};
+
+    fn trial(
+        condition: Condition,
+        sex: Sex,
+        correct_response: bool,
+        response_time_milliseconds: i64,
+        block: usize,
+    ) -> Trial {
+        Trial {
+            correct_response,
+            condition,
+            sex,
+            response_time_milliseconds: if correct_response {
+                Some(response_time_milliseconds)
+            } else {
+                None
+            },
+            button_press_time_microseconds: Some(response_time_milliseconds * 1000),
+            block,
+            ..Default::default()
+        }
+    }
+
+    fn trials() -> Vec<Trial> {
+        vec![
+            trial(Condition::Angry, Sex::Male, true, 500, 1),
+            trial(Condition::Angry, Sex::Female, false, 550, 1),
+            trial(Condition::Happy, Sex::Female, true, 700, 1),
+            trial(Condition::Happy, Sex::Male, true, 520, 1),
+            trial(Condition::Neutral, Sex::Male, false, 600, 2),
+            trial(Condition::Neutral, Sex::Male, true, 660, 2),
+        ]
+    }
+
+    fn reaction_time(trials: &[Trial]) -> Option<f64> {
+        crate::reaction_time_milliseconds(trials).map(|milliseconds| milliseconds as f64)
+    }
+
+    #[test]
+    fn post_error_slowing() {
+        let (after_error, after_correct) = SequentialEffect::PostErrorSlowing.split(&trials());
+        assert_eq!(2, after_error.len());
+        assert_eq!(2, after_correct.len());
+        assert_eq!(
+            Some(680. - 520.),
+            SequentialEffect::PostErrorSlowing.difference(&trials(), reaction_time)
+        );
+    }
+
+    #[test]
+    fn emotion_switch_cost() {
+        assert_eq!(
+            Some(700. - 590.),
+            SequentialEffect::EmotionSwitchCost.difference(&trials(), reaction_time)
+        );
+    }
+
+    #[test]
+    fn sex_switch_cost() {
+        assert_eq!(
+            Some(520. - 680.),
+            SequentialEffect::SexSwitchCost.difference(&trials(), reaction_time)
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use crate::statistics::normal_quantile;
+use crate::{Sex, Trial};
+
+/// Sensitivity and bias for telling male from female faces, treating a male
+/// face as the signal and a press of the male button as a "yes". Rates use
+/// the log-linear correction (add 0.5 to each count and 1 to each total) so
+/// perfect or empty rates stay finite.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SignalDetection {
+    pub hit_rate: f64,
+    pub false_alarm_rate: f64,
+    pub d_prime: f64,
+    /// Negative values mean a bias toward answering "male".
+    pub criterion: f64,
+    /// Natural log of the likelihood ratio at the criterion; negative values
+    /// also mean a bias toward answering "male".
+    pub log_beta: f64,
+}
+
+/// Whether the scored press answered "male", or `None` if nothing, or both
+/// buttons, were pressed.
+fn responded_male(trial: &Trial) -> Option<bool> {
+    let pressed_correct_button = trial.response_button? == trial.correct_button?;
+    Some((trial.sex == Sex::Male) == pressed_correct_button)
+}
+
+fn corrected_rate(trials: &[&Trial]) -> Option<f64> {
+    if trials.is_empty() {
+        return None;
+    }
+    let male_responses = trials
+        .iter()
+        .filter(|trial| responded_male(trial) == Some(true))
+        .count();
+    Some((male_responses as f64 + 0.5) / (trials.len() as f64 + 1.))
+}
+
+/// Trials without a single button press are left out. `None` if there are no
+/// responded trials for either face sex.
+pub fn signal_detection(trials: &[Trial]) -> Option<SignalDetection> {
+    let responded = trials
+        .iter()
+        .filter(|trial| responded_male(trial).is_some());
+    let (male_faces, female_faces): (Vec<&Trial>, Vec<&Trial>) =
+        responded.partition(|trial| trial.sex == Sex::Male);
+    let hit_rate = corrected_rate(&male_faces)?;
+    let false_alarm_rate = corrected_rate(&female_faces)?;
+    let z_hit = normal_quantile(hit_rate);
+    let z_false_alarm = normal_quantile(false_alarm_rate);
+    let d_prime = z_hit - z_false_alarm;
+    let criterion = -(z_hit + z_false_alarm) / 2.;
+    Some(SignalDetection {
+        hit_rate,
+        false_alarm_rate,
+        d_prime,
+        criterion,
+        log_beta: d_prime * criterion,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::triggers::Button;
+    use crate::{
+        reconstruct_trials_with_options, Condition, Event, ReconstructionOptions, ResponseMapping,
+        ResponsePolicy, Sex, Trial,
+    };
+
+    fn trial(sex: Sex, correct_response: bool, responded: bool) -> Trial {
+        let correct_button = if sex == Sex::Male {
+            Button::One
+        } else {
+            Button::Two
+        };
+        Trial {
+            correct_response,
+            condition: Condition::Angry,
+            sex,
+            response_time_milliseconds: if correct_response { Some(600) } else { None },
+            button_press_time_microseconds: if responded { Some(600_000) } else { None },
+            response_button: responded.then(|| {
+                if correct_response {
+                    correct_button
+                } else {
+                    correct_button.other()
+                }
+            }),
+            correct_button: Some(correct_button),
+            ..Default::default()
+        }
+    }
+
+    fn round(value: f64) -> f64 {
+        (value * 1e4).round() / 1e4
+    }
+
+    #[test]
+    fn signal_detection() {
+        let mut trials = Vec::new();
+        trials.extend((0..8).map(|_| trial(Sex::Male, true, true)));
+        trials.extend((0..2).map(|_| trial(Sex::Male, false, true)));
+        trials.extend((0..6).map(|_| trial(Sex::Female, true, true)));
+        trials.extend((0..4).map(|_| trial(Sex::Female, false, true)));
+        trials.push(trial(Sex::Female, false, false));
+        trials.push(Trial {
+            both_buttons: true,
+            response_button: None,
+            ..trial(Sex::Male, false, true)
+        });
+        let detection = crate::signal_detection::signal_detection(&trials).unwrap();
+        assert_eq!(8.5 / 11., detection.hit_rate);
+        assert_eq!(4.5 / 11., detection.false_alarm_rate);
+        assert_eq!(0.9777, round(detection.d_prime));
+        assert_eq!(-0.259, round(detection.criterion));
+        assert_eq!(-0.2532, round(detection.log_beta));
+    }
+
+    #[test]
+    fn perfect_performance_stays_finite() {
+        let trials = vec![trial(Sex::Male, true, true), trial(Sex::Female, true, true)];
+        let detection = crate::signal_detection::signal_detection(&trials).unwrap();
+        assert_eq!(0.75, detection.hit_rate);
+        assert_eq!(0.25, detection.false_alarm_rate);
+        assert!(detection.d_prime.is_finite());
+    }
+
+    #[test]
+    fn one_face_sex_only() {
+        assert_eq!(
+            None,
+            crate::signal_detection::signal_detection(&[trial(Sex::Male, true, true)])
+        );
+    }
+
+    #[test]
+    fn responses_follow_the_scored_button() {
+        // A male face answered right, then wrong, and a female face answered
+        // wrong, all with the buttons swapped.
+        let events = [
+            (5_000_000, 31),
+            (5_010_000, 4127),
+            (5_600_000, 512),
+            (5_900_000, 256),
+            (8_000_000, 21),
+            (8_010_000, 4117),
+            (8_600_000, 512),
+        ]
+        .into_iter()
+        .map(|(time_microseconds, trigger_code)| Event {
+            time_microseconds,
+            trigger_code,
+        })
+        .collect::<Vec<_>>();
+        let trials = reconstruct_trials_with_options(
+            events,
+            &ReconstructionOptions {
+                mapping: ResponseMapping::Swapped,
+                response_policy: ResponsePolicy::AnyWrong,
+                ..Default::default()
+            },
+        );
+        assert!(!trials[0].correct_response);
+        assert_eq!(
+            vec![Some(true), Some(true)],
+            trials.iter().map(super::responded_male).collect::<Vec<_>>()
+        );
+        let detection = crate::signal_detection::signal_detection(&trials).unwrap();
+        assert_eq!(1.5 / 2., detection.hit_rate);
+        assert_eq!(1.5 / 2., detection.false_alarm_rate);
+    }
+}
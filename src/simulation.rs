@@ -0,0 +1,574 @@
+use crate::anova::CELLS;
+use crate::bootstrap::Rng;
+use crate::triggers::Button;
+use crate::{Condition, OnsetReference, Sex, Trial};
+
+const SESSION_START_MICROSECONDS: i64 = 5_000_000;
+const PHOTODIODE: i32 = 4096;
+// Male faces are answered with button 1 and female faces with button 2.
+const BUTTON_1: i32 = 256;
+const BUTTON_2: i32 = 512;
+const BREAK_MICROSECONDS: i64 = 15_000_000;
+// The display leaves a lone visual onset this long before the next trial
+// would have started when a break begins.
+const BREAK_VISUAL_BEFORE_MICROSECONDS: i64 = 300_000;
+// Glitch visual onsets come this long before the next trial starts.
+const LONE_VISUAL_BEFORE_MICROSECONDS: i64 = 800_000;
+// Reaction times are clamped so every press lands in its own trial, before
+// any lone visual onset.
+const MINIMUM_REACTION_TIME_MILLISECONDS: f64 = 150.;
+const RESPONSE_DEADLINE_BEFORE_MICROSECONDS: i64 = 1_000_000;
+const DUPLICATE_AFTER_MICROSECONDS: i64 = 1_000;
+
+/// In what order the cells are shown.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ConditionOrder {
+    /// An equal number of trials per cell, shuffled.
+    Random,
+    /// Through `anova::CELLS` in turn.
+    Cycled,
+    /// Every trial of one cell before the next, in `anova::CELLS` order.
+    Blocked,
+}
+
+impl std::str::FromStr for ConditionOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "random" => Ok(ConditionOrder::Random),
+            "cycled" => Ok(ConditionOrder::Cycled),
+            "blocked" => Ok(ConditionOrder::Blocked),
+            _ => Err(format!("unknown condition order: {}", s)),
+        }
+    }
+}
+
+/// Ex-Gaussian reaction time distribution, in milliseconds.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ReactionTimeDistribution {
+    pub mu: f64,
+    pub sigma: f64,
+    pub tau: f64,
+}
+
+/// Parses "<mu>,<sigma>,<tau>".
+impl std::str::FromStr for ReactionTimeDistribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parameters = s
+            .split(',')
+            .map(|parameter| {
+                parameter
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|&value| value >= 0.)
+            })
+            .collect::<Option<Vec<_>>>();
+        match parameters.as_deref() {
+            Some(&[mu, sigma, tau]) => Ok(ReactionTimeDistribution { mu, sigma, tau }),
+            _ => Err(format!(
+                "expected <mu>,<sigma>,<tau> in milliseconds: {}",
+                s
+            )),
+        }
+    }
+}
+
+/// How one emotion and face sex is answered.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CellParameters {
+    pub reaction_time: ReactionTimeDistribution,
+    /// Proportion of answers given with the wrong button.
+    pub error_rate: f64,
+}
+
+impl Default for CellParameters {
+    fn default() -> Self {
+        CellParameters {
+            reaction_time: ReactionTimeDistribution {
+                mu: 500.,
+                sigma: 60.,
+                tau: 120.,
+            },
+            error_rate: 0.08,
+        }
+    }
+}
+
+/// A setting for one cell, given as "Angry Male=<value>", or for every cell
+/// when given without a cell name.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CellSetting<T> {
+    /// Index into `anova::CELLS`.
+    pub cell: Option<usize>,
+    pub value: T,
+}
+
+impl<T> std::str::FromStr for CellSetting<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<T>()
+                .map_err(|why| format!("{}: {}", why, value.trim()))
+        };
+        let Some((name, value)) = s.split_once('=') else {
+            return Ok(CellSetting {
+                cell: None,
+                value: parse(s)?,
+            });
+        };
+        let cell = CELLS
+            .iter()
+            .position(|(condition, sex)| {
+                format!("{:?} {:?}", condition, sex).eq_ignore_ascii_case(name.trim())
+            })
+            .ok_or_else(|| format!("unknown cell: {}", name.trim()))?;
+        Ok(CellSetting {
+            cell: Some(cell),
+            value: parse(value)?,
+        })
+    }
+}
+
+impl<T: Clone> CellSetting<T> {
+    /// Sets the value on the cell it names, or on every cell.
+    pub fn apply(&self, cells: &mut [CellParameters], set: impl Fn(&mut CellParameters, T)) {
+        for (index, cell) in cells.iter_mut().enumerate() {
+            if self.cell.is_none_or(|setting_cell| setting_cell == index) {
+                set(cell, self.value.clone());
+            }
+        }
+    }
+}
+
+/// Probability of each recording fault per trial.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Glitches {
+    /// The condition trigger sent twice, a millisecond apart.
+    pub duplicate_triggers: f64,
+    /// An extra visual onset after the response, as the display sometimes
+    /// sends.
+    pub lone_visuals: f64,
+    pub missing_responses: f64,
+    /// Both buttons pressed at once (768).
+    pub both_buttons: f64,
+}
+
+/// A session of the paradigm to generate, with the standard response
+/// mapping. Every trial has its condition trigger and a separate visual
+/// onset, except the first after a break, whose condition code arrives
+/// combined with the visual onset after the lone onset the display leaves
+/// at the start of the break.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Simulation {
+    pub trials: usize,
+    /// Separated by 15 s breaks.
+    pub blocks: usize,
+    pub order: ConditionOrder,
+    /// In `anova::CELLS` order.
+    pub cells: [CellParameters; 6],
+    pub glitches: Glitches,
+    /// From one trial's condition trigger to the next, before jitter.
+    pub stimulus_onset_asynchrony_microseconds: i64,
+    /// Up to this much is added to each stimulus onset asynchrony.
+    pub jitter_microseconds: i64,
+    /// From the condition trigger to the visual onset.
+    pub visual_delay_microseconds: i64,
+    pub seed: u64,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation {
+            trials: 240,
+            blocks: 2,
+            order: ConditionOrder::Random,
+            cells: [CellParameters::default(); 6],
+            glitches: Glitches::default(),
+            stimulus_onset_asynchrony_microseconds: 3_000_000,
+            jitter_microseconds: 500_000,
+            visual_delay_microseconds: 16_000,
+            seed: 1,
+        }
+    }
+}
+
+/// A generated BESA .evt file and the trials `reconstruct_trials` should
+/// find in it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SimulatedRecording {
+    pub contents: String,
+    pub trials: Vec<Trial>,
+}
+
+fn condition_code(condition: &Condition, sex: &Sex) -> i32 {
+    let emotion = match condition {
+        Condition::Angry => 1,
+        Condition::Happy => 2,
+        Condition::Neutral => 3,
+    };
+    match sex {
+        Sex::Male => 30 + emotion,
+        Sex::Female => 20 + emotion,
+    }
+}
+
+impl Simulation {
+    /// An error for settings `generate` cannot honour.
+    pub fn check(&self) -> Result<(), String> {
+        if self.blocks == 0 || self.blocks > self.trials {
+            return Err("--blocks must be between 1 and the number of trials".to_string());
+        }
+        let probabilities = self
+            .cells
+            .iter()
+            .zip(&CELLS)
+            .map(|(cell, (condition, sex))| {
+                (
+                    format!("{:?} {:?} error rate", condition, sex),
+                    cell.error_rate,
+                )
+            })
+            .chain([
+                (
+                    "duplicate trigger rate".to_string(),
+                    self.glitches.duplicate_triggers,
+                ),
+                ("lone visual rate".to_string(), self.glitches.lone_visuals),
+                (
+                    "missing response rate".to_string(),
+                    self.glitches.missing_responses,
+                ),
+                ("both buttons rate".to_string(), self.glitches.both_buttons),
+                (
+                    "missing response and both buttons rate together".to_string(),
+                    self.glitches.missing_responses + self.glitches.both_buttons,
+                ),
+            ]);
+        for (name, probability) in probabilities {
+            if !(0. ..=1.).contains(&probability) {
+                return Err(format!("{} must be between 0 and 1: {}", name, probability));
+            }
+        }
+        Ok(())
+    }
+
+    /// Indices into `anova::CELLS`, one per trial.
+    fn cell_order(&self, rng: &mut Rng) -> Vec<usize> {
+        let mut cells = (0..self.trials)
+            .map(|trial| trial % CELLS.len())
+            .collect::<Vec<_>>();
+        match self.order {
+            ConditionOrder::Random => {
+                for index in (1..cells.len()).rev() {
+                    cells.swap(index, rng.below(index + 1));
+                }
+            }
+            ConditionOrder::Cycled => {}
+            ConditionOrder::Blocked => cells.sort(),
+        }
+        cells
+    }
+
+    fn reaction_time_milliseconds(&self, cell: usize, rng: &mut Rng) -> f64 {
+        let distribution = self.cells[cell].reaction_time;
+        let normal =
+            (-2. * rng.uniform().ln()).sqrt() * (2. * std::f64::consts::PI * rng.uniform()).cos();
+        let exponential = -rng.uniform().ln();
+        let deadline = (self.stimulus_onset_asynchrony_microseconds
+            - RESPONSE_DEADLINE_BEFORE_MICROSECONDS
+            - self.visual_delay_microseconds) as f64
+            / 1000.;
+        (distribution.mu + distribution.sigma * normal + distribution.tau * exponential)
+            .clamp(MINIMUM_REACTION_TIME_MILLISECONDS, deadline)
+    }
+
+    pub fn generate(&self) -> SimulatedRecording {
+        let mut rng = Rng::new(self.seed);
+        let mut lines = vec![
+            "Tmu         \tCode\tTriNo\tComnt\tVer-C".to_string(),
+            format!("0        \t11\t0\tSimulated session, seed {}", self.seed),
+        ];
+        let mut trigger = |time: i64, code: i32| {
+            lines.push(format!(
+                "{}        \t1\t{}\tFIFF Trigger: {}",
+                time, code, code
+            ))
+        };
+        let mut trials = Vec::new();
+        let mut onset = SESSION_START_MICROSECONDS;
+        for (index, cell) in self.cell_order(&mut rng).into_iter().enumerate() {
+            let block = index * self.blocks / self.trials + 1;
+            let after_break =
+                index > 0 && block != trials.last().map_or(1, |trial: &Trial| trial.block);
+            let (condition, sex) = &CELLS[cell];
+            let code = condition_code(condition, sex);
+            let visual = if after_break {
+                trigger(onset, code | PHOTODIODE);
+                onset
+            } else {
+                trigger(onset, code);
+                if rng.uniform() < self.glitches.duplicate_triggers {
+                    trigger(onset + DUPLICATE_AFTER_MICROSECONDS, code);
+                }
+                trigger(onset + self.visual_delay_microseconds, PHOTODIODE);
+                onset + self.visual_delay_microseconds
+            };
+            let correct_button = if *sex == Sex::Male {
+                BUTTON_1
+            } else {
+                BUTTON_2
+            };
+            let outcome = rng.uniform();
+            let press = if outcome < self.glitches.missing_responses {
+                None
+            } else if outcome < self.glitches.missing_responses + self.glitches.both_buttons {
+                Some(BUTTON_1 | BUTTON_2)
+            } else if rng.uniform() < self.cells[cell].error_rate {
+                Some(BUTTON_1 + BUTTON_2 - correct_button)
+            } else {
+                Some(correct_button)
+            };
+            let reaction_time = self.reaction_time_milliseconds(cell, &mut rng);
+            let press_time = visual + (reaction_time * 1000.).round() as i64;
+            if let Some(code) = press {
+                trigger(press_time, code);
+            }
+            let next_onset = onset
+                + self.stimulus_onset_asynchrony_microseconds
+                + rng.below(self.jitter_microseconds as usize + 1) as i64;
+            if rng.uniform() < self.glitches.lone_visuals {
+                trigger(next_onset - LONE_VISUAL_BEFORE_MICROSECONDS, PHOTODIODE);
+            }
+            let correct_response = press == Some(correct_button);
+            trials.push(Trial {
+                correct_response,
+                condition: condition.clone(),
+                sex: sex.clone(),
+                response_time_milliseconds: if correct_response {
+                    Some((press_time - visual + 500) / 1000)
+                } else {
+                    None
+                },
+                stimulus_time_microseconds: visual,
+                button_press_time_microseconds: press.map(|_| press_time),
+                artifact: false,
+                block,
+                onset_reference: OnsetReference::Photodiode,
+                extra_presses: 0,
+                both_buttons: press == Some(BUTTON_1 | BUTTON_2),
+                response_button: match press {
+                    Some(BUTTON_1) => Some(Button::One),
+                    Some(BUTTON_2) => Some(Button::Two),
+                    _ => None,
+                },
+                correct_button: Some(if *sex == Sex::Male {
+                    Button::One
+                } else {
+                    Button::Two
+                }),
+            });
+            onset = next_onset;
+            if (index + 1) * self.blocks / self.trials + 1 != block && index + 1 < self.trials {
+                trigger(onset - BREAK_VISUAL_BEFORE_MICROSECONDS, PHOTODIODE);
+                onset += BREAK_MICROSECONDS;
+            }
+        }
+        lines.push(String::new());
+        SimulatedRecording {
+            contents: lines.join("\n"),
+            trials,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CellParameters, CellSetting, ConditionOrder, Glitches, ReactionTimeDistribution, Simulation,
+    };
+    use crate::anova::CELLS;
+    use crate::{parse_events, reconstruct_trials};
+
+    #[test]
+    fn reconstructs_to_ground_truth() {
+        let recording = Simulation::default().generate();
+        assert_eq!(240, recording.trials.len());
+        for (condition, sex) in &CELLS {
+            assert_eq!(
+                40,
+                recording
+                    .trials
+                    .iter()
+                    .filter(|trial| trial.condition == *condition && trial.sex == *sex)
+                    .count()
+            );
+        }
+        assert_eq!(2, recording.trials.last().unwrap().block);
+        assert_eq!(
+            recording.trials,
+            reconstruct_trials(parse_events(&recording.contents))
+        );
+    }
+
+    #[test]
+    fn reconstructs_through_glitches() {
+        let simulation = Simulation {
+            blocks: 4,
+            glitches: Glitches {
+                duplicate_triggers: 0.1,
+                lone_visuals: 0.1,
+                missing_responses: 0.1,
+                both_buttons: 0.1,
+            },
+            seed: 3,
+            ..Default::default()
+        };
+        let recording = simulation.generate();
+        assert!(recording.trials.iter().any(|trial| trial.both_buttons));
+        assert!(recording
+            .trials
+            .iter()
+            .any(|trial| trial.button_press_time_microseconds.is_none()));
+        assert_eq!(
+            recording.trials,
+            reconstruct_trials(parse_events(&recording.contents))
+        );
+        assert_eq!(recording, simulation.generate());
+    }
+
+    #[test]
+    fn condition_orders() {
+        let cycled = Simulation {
+            trials: 12,
+            order: ConditionOrder::Cycled,
+            ..Default::default()
+        }
+        .generate();
+        assert_eq!(
+            CELLS.iter().chain(&CELLS).cloned().collect::<Vec<_>>(),
+            cycled
+                .trials
+                .iter()
+                .map(|trial| (trial.condition.clone(), trial.sex.clone()))
+                .collect::<Vec<_>>()
+        );
+        let blocked = Simulation {
+            trials: 12,
+            order: ConditionOrder::Blocked,
+            ..Default::default()
+        }
+        .generate();
+        assert_eq!(blocked.trials[0].condition, blocked.trials[1].condition);
+        assert_eq!(blocked.trials[0].sex, blocked.trials[1].sex);
+    }
+
+    #[test]
+    fn error_rate_and_reaction_times() {
+        let mut simulation = Simulation {
+            trials: 1200,
+            blocks: 1,
+            ..Default::default()
+        };
+        simulation.cells[0].error_rate = 0.5;
+        let trials = simulation.generate().trials;
+        let angry_male = trials
+            .iter()
+            .filter(|trial| trial.condition == CELLS[0].0 && trial.sex == CELLS[0].1)
+            .collect::<Vec<_>>();
+        let errors = angry_male
+            .iter()
+            .filter(|trial| !trial.correct_response)
+            .count();
+        assert!((70..130).contains(&errors), "errors {}", errors);
+        let correct = trials
+            .iter()
+            .filter_map(|trial| trial.response_time_milliseconds)
+            .collect::<Vec<_>>();
+        let mean = correct.iter().sum::<i64>() as f64 / correct.len() as f64;
+        assert!((mean - 620.).abs() < 15., "mean {}", mean);
+    }
+
+    #[test]
+    fn parse_cell_settings() {
+        assert_eq!(
+            Ok(CellSetting {
+                cell: Some(4),
+                value: ReactionTimeDistribution {
+                    mu: 450.,
+                    sigma: 50.,
+                    tau: 100.
+                }
+            }),
+            "happy female=450, 50, 100".parse()
+        );
+        assert_eq!(
+            Ok(CellSetting {
+                cell: None,
+                value: 0.1
+            }),
+            "0.1".parse()
+        );
+        assert!("Sad Male=0.1".parse::<CellSetting<f64>>().is_err());
+        assert!("450,50".parse::<ReactionTimeDistribution>().is_err());
+    }
+
+    #[test]
+    fn apply_cell_settings() {
+        let mut cells = [CellParameters::default(); 6];
+        "Angry Female=0.3"
+            .parse::<CellSetting<f64>>()
+            .unwrap()
+            .apply(&mut cells, |cell, error_rate| cell.error_rate = error_rate);
+        assert_eq!(0.3, cells[3].error_rate);
+        assert_eq!(0.08, cells[0].error_rate);
+        "0.2"
+            .parse::<CellSetting<f64>>()
+            .unwrap()
+            .apply(&mut cells, |cell, error_rate| cell.error_rate = error_rate);
+        assert!(cells.iter().all(|cell| cell.error_rate == 0.2));
+    }
+
+    #[test]
+    fn check_probabilities() {
+        assert_eq!(Ok(()), Simulation::default().check());
+        let mut simulation = Simulation::default();
+        simulation.cells[3].error_rate = 1.5;
+        assert_eq!(
+            Err("Angry Female error rate must be between 0 and 1: 1.5".to_string()),
+            simulation.check()
+        );
+        let simulation = Simulation {
+            glitches: Glitches {
+                missing_responses: 0.6,
+                both_buttons: 0.5,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(simulation.check().is_err());
+        let simulation = Simulation {
+            glitches: Glitches {
+                lone_visuals: -0.1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(simulation.check().is_err());
+        let simulation = Simulation {
+            blocks: 0,
+            ..Default::default()
+        };
+        assert!(simulation.check().is_err());
+    }
+}
@@ -0,0 +1,473 @@
+use crate::Trial;
+
+pub fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+/// Sample standard deviation, with n - 1 in the denominator.
+pub fn standard_deviation(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let mean = mean(values)?;
+    let sum_of_squares = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>();
+    Some((sum_of_squares / (values.len() - 1) as f64).sqrt())
+}
+
+pub fn median(values: &[f64]) -> Option<f64> {
+    percentile(values, 50.)
+}
+
+/// Linearly interpolated percentile, matching R's default (type 7).
+pub fn percentile(values: &[f64], percent: f64) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let position = percent / 100. * (sorted.len() - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    Some(sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64))
+}
+
+/// Inverse of the standard normal cumulative distribution function, using
+/// Acklam's rational approximation (relative error below 1.2e-9).
+pub fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e+01,
+        2.209460984245205e+02,
+        -2.759285104469687e+02,
+        1.38357751867269e+02,
+        -3.066479806614716e+01,
+        2.506628277459239e+00,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e+01,
+        1.615858368580409e+02,
+        -1.556989798598866e+02,
+        6.680131188771972e+01,
+        -1.328068155288572e+01,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-03,
+        -3.223964580411365e-01,
+        -2.400758277161838e+00,
+        -2.549732539343734e+00,
+        4.374664141464968e+00,
+        2.938163982698783e+00,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-03,
+        3.224671290700398e-01,
+        2.445134137142996e+00,
+        3.754408661907416e+00,
+    ];
+    const LOW: f64 = 0.02425;
+    if p <= 0. {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1. {
+        return f64::INFINITY;
+    }
+    if p < LOW {
+        let q = (-2. * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.)
+    } else if p <= 1. - LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.)
+    } else {
+        -normal_quantile(1. - p)
+    }
+}
+
+/// Natural log of the standard normal cumulative distribution function. The
+/// complementary error function is from the Chebyshev fit in Numerical
+/// Recipes (fractional error below 1.2e-7), kept in log form so the far lower
+/// tail does not underflow.
+pub fn ln_normal_cdf(z: f64) -> f64 {
+    let x = -z / std::f64::consts::SQRT_2;
+    let t = 1. / (1. + 0.5 * x.abs());
+    let exponent = -x * x - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398
+                                + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let ln_erfc = if x >= 0. {
+        t.ln() + exponent
+    } else {
+        (2. - t * exponent.exp()).ln()
+    };
+    ln_erfc - std::f64::consts::LN_2
+}
+
+/// Natural log of the gamma function, by the Lanczos approximation.
+pub fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let mut series = 1.000000000190015;
+    for (index, coefficient) in COEFFICIENTS.iter().enumerate() {
+        series += coefficient / (x + 1. + index as f64);
+    }
+    let t = x + 5.5;
+    (2.5066282746310005 * series / x).ln() + (x + 0.5) * t.ln() - t
+}
+
+// Continued fraction for the incomplete beta function, evaluated by the
+// modified Lentz method.
+fn incomplete_beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.;
+    let mut d = 1. - (a + b) * x / (a + 1.);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1. / d;
+    let mut fraction = d;
+    for m in 1..300 {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2. * m - 1.) * (a + 2. * m));
+        d = 1. + even * d;
+        d = if d.abs() < TINY { TINY } else { d };
+        c = 1. + even / c;
+        c = if c.abs() < TINY { TINY } else { c };
+        d = 1. / d;
+        fraction *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2. * m) * (a + 2. * m + 1.));
+        d = 1. + odd * d;
+        d = if d.abs() < TINY { TINY } else { d };
+        c = 1. + odd / c;
+        c = if c.abs() < TINY { TINY } else { c };
+        d = 1. / d;
+        let delta = d * c;
+        fraction *= delta;
+        if (delta - 1.).abs() < 1e-14 {
+            break;
+        }
+    }
+    fraction
+}
+
+/// The regularized incomplete beta function I_x(a, b).
+pub fn regularized_incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0. {
+        return 0.;
+    }
+    if x >= 1. {
+        return 1.;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1. - x).ln()).exp();
+    if x < (a + 1.) / (a + b + 2.) {
+        front * incomplete_beta_continued_fraction(a, b, x) / a
+    } else {
+        1. - front * incomplete_beta_continued_fraction(b, a, 1. - x) / b
+    }
+}
+
+pub fn student_t_cdf(t: f64, degrees_of_freedom: f64) -> f64 {
+    let tail = 0.5
+        * regularized_incomplete_beta(
+            degrees_of_freedom / 2.,
+            0.5,
+            degrees_of_freedom / (degrees_of_freedom + t * t),
+        );
+    if t > 0. {
+        1. - tail
+    } else {
+        tail
+    }
+}
+
+/// Inverse of `student_t_cdf`, found by bisection.
+pub fn student_t_quantile(p: f64, degrees_of_freedom: f64) -> f64 {
+    let (mut lower, mut upper) = (-1e6, 1e6);
+    for _ in 0..200 {
+        let middle = (lower + upper) / 2.;
+        if student_t_cdf(middle, degrees_of_freedom) < p {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+    (lower + upper) / 2.
+}
+
+/// Descriptive statistics of the correct-response reaction times in a group
+/// of trials, from the unrounded event times.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReactionTimeSummary {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub standard_deviation: Option<f64>,
+    pub interquartile_range: f64,
+    pub percentile_10: f64,
+    pub percentile_90: f64,
+    pub coefficient_of_variation: Option<f64>,
+}
+
+/// Correct-response reaction times in milliseconds, from the unrounded event
+/// times.
+pub fn correct_reaction_times(trials: &[Trial]) -> Vec<f64> {
+    trials
+        .iter()
+        .filter(|trial| trial.correct_response)
+        .filter_map(|trial| {
+            trial
+                .button_press_time_microseconds
+                .map(|time| (time - trial.stimulus_time_microseconds) as f64 / 1000.)
+        })
+        .collect::<Vec<_>>()
+}
+
+impl ReactionTimeSummary {
+    pub fn from_trials(trials: &[Trial]) -> Option<ReactionTimeSummary> {
+        let reaction_times = correct_reaction_times(trials);
+        let mean = mean(&reaction_times)?;
+        let standard_deviation = standard_deviation(&reaction_times);
+        Some(ReactionTimeSummary {
+            count: reaction_times.len(),
+            mean,
+            median: median(&reaction_times)?,
+            standard_deviation,
+            interquartile_range: percentile(&reaction_times, 75.)?
+                - percentile(&reaction_times, 25.)?,
+            percentile_10: percentile(&reaction_times, 10.)?,
+            percentile_90: percentile(&reaction_times, 90.)?,
+            coefficient_of_variation: standard_deviation.map(|sd| sd / mean),
+        })
+    }
+
+    pub fn get(&self, statistic: &ReactionTimeStatistic) -> Option<f64> {
+        match statistic {
+            ReactionTimeStatistic::Count => Some(self.count as f64),
+            ReactionTimeStatistic::Mean => Some(self.mean),
+            ReactionTimeStatistic::Median => Some(self.median),
+            ReactionTimeStatistic::StandardDeviation => self.standard_deviation,
+            ReactionTimeStatistic::InterquartileRange => Some(self.interquartile_range),
+            ReactionTimeStatistic::Percentile10 => Some(self.percentile_10),
+            ReactionTimeStatistic::Percentile90 => Some(self.percentile_90),
+            ReactionTimeStatistic::CoefficientOfVariation => self.coefficient_of_variation,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ReactionTimeStatistic {
+    Count,
+    Mean,
+    Median,
+    StandardDeviation,
+    InterquartileRange,
+    Percentile10,
+    Percentile90,
+    CoefficientOfVariation,
+}
+
+impl ReactionTimeStatistic {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReactionTimeStatistic::Count => "RT N",
+            ReactionTimeStatistic::Mean => "RT Mean (ms)",
+            ReactionTimeStatistic::Median => "RT Median (ms)",
+            ReactionTimeStatistic::StandardDeviation => "RT SD (ms)",
+            ReactionTimeStatistic::InterquartileRange => "RT IQR (ms)",
+            ReactionTimeStatistic::Percentile10 => "RT 10th Percentile (ms)",
+            ReactionTimeStatistic::Percentile90 => "RT 90th Percentile (ms)",
+            ReactionTimeStatistic::CoefficientOfVariation => "RT CV",
+        }
+    }
+}
+
+impl std::str::FromStr for ReactionTimeStatistic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "n" => Ok(ReactionTimeStatistic::Count),
+            "mean" => Ok(ReactionTimeStatistic::Mean),
+            "median" => Ok(ReactionTimeStatistic::Median),
+            "sd" => Ok(ReactionTimeStatistic::StandardDeviation),
+            "iqr" => Ok(ReactionTimeStatistic::InterquartileRange),
+            "p10" => Ok(ReactionTimeStatistic::Percentile10),
+            "p90" => Ok(ReactionTimeStatistic::Percentile90),
+            "cv" => Ok(ReactionTimeStatistic::CoefficientOfVariation),
+            _ => Err(format!("unknown reaction time statistic: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReactionTimeStatistic, ReactionTimeSummary};
+    use crate::{Condition, Sex, Trial};
+
+    fn trial(correct_response: bool, response_time_microseconds: i64) -> Trial {
+        Trial {
+            correct_response,
+            condition: Condition::Happy,
+            sex: Sex::Male,
+            response_time_milliseconds: if correct_response {
+                Some((response_time_microseconds + 500) / 1000)
+            } else {
+                None
+            },
+            stimulus_time_microseconds: 1_000_000,
+            button_press_time_microseconds: Some(1_000_000 + response_time_microseconds),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn mean() {
+        assert_eq!(Some(2.5), crate::statistics::mean(&[1., 2., 3., 4.]));
+        assert_eq!(None, crate::statistics::mean(&[]));
+    }
+
+    #[test]
+    fn standard_deviation() {
+        assert_eq!(
+            Some(2.5f64.sqrt()),
+            crate::statistics::standard_deviation(&[1., 2., 3., 4., 5.])
+        );
+        assert_eq!(None, crate::statistics::standard_deviation(&[1.]));
+    }
+
+    #[test]
+    fn median() {
+        assert_eq!(Some(3.), crate::statistics::median(&[5., 1., 3.]));
+        assert_eq!(Some(2.5), crate::statistics::median(&[4., 1., 3., 2.]));
+    }
+
+    #[test]
+    fn percentile() {
+        assert_eq!(
+            Some(1.9),
+            crate::statistics::percentile(&[1., 2., 3., 4., 5., 6., 7., 8., 9., 10.], 10.)
+        );
+        assert_eq!(
+            Some(9.1),
+            crate::statistics::percentile(&[1., 2., 3., 4., 5., 6., 7., 8., 9., 10.], 90.)
+                .map(|value| (value * 1e9).round() / 1e9)
+        );
+    }
+
+    #[test]
+    fn normal_quantile() {
+        assert_eq!(0., crate::statistics::normal_quantile(0.5));
+        assert_eq!(
+            1.959964,
+            (crate::statistics::normal_quantile(0.975) * 1e6).round() / 1e6
+        );
+        assert_eq!(
+            -2.326348,
+            (crate::statistics::normal_quantile(0.01) * 1e6).round() / 1e6
+        );
+    }
+
+    #[test]
+    fn ln_normal_cdf() {
+        assert_eq!(-1.841022, round(crate::statistics::ln_normal_cdf(-1.), 6));
+        assert_eq!(
+            -0.025318,
+            round(crate::statistics::ln_normal_cdf(1.959964), 6)
+        );
+        // ln of 7.6e-24, which would be lost to rounding if the tail were
+        // computed as 1 - erfc.
+        assert_eq!(-53.23, round(crate::statistics::ln_normal_cdf(-10.), 2));
+    }
+
+    fn round(value: f64, decimals: i32) -> f64 {
+        let scale = 10f64.powi(decimals);
+        (value * scale).round() / scale
+    }
+
+    #[test]
+    fn regularized_incomplete_beta() {
+        assert_eq!(
+            0.5,
+            round(
+                crate::statistics::regularized_incomplete_beta(2., 2., 0.5),
+                12
+            )
+        );
+        assert_eq!(
+            0.1808,
+            round(
+                crate::statistics::regularized_incomplete_beta(2., 3., 0.2),
+                12
+            )
+        );
+    }
+
+    #[test]
+    fn student_t() {
+        assert_eq!(0.5, crate::statistics::student_t_cdf(0., 5.));
+        assert_eq!(
+            2.228139,
+            round(crate::statistics::student_t_quantile(0.975, 10.), 6)
+        );
+        assert_eq!(
+            12.706205,
+            round(crate::statistics::student_t_quantile(0.975, 1.), 6)
+        );
+    }
+
+    #[test]
+    fn reaction_time_summary() {
+        let summary = ReactionTimeSummary::from_trials(&[
+            trial(true, 400_500),
+            trial(true, 500_000),
+            trial(false, 250_000),
+            trial(true, 600_000),
+            trial(true, 700_000),
+            trial(true, 800_000),
+        ])
+        .unwrap();
+        assert_eq!(5, summary.count);
+        assert_eq!(600.1, summary.mean);
+        assert_eq!(600., summary.median);
+        assert_eq!(200., summary.interquartile_range);
+        assert_eq!(440.3, (summary.percentile_10 * 1e9).round() / 1e9);
+        assert_eq!(760., summary.percentile_90);
+        assert_eq!(Some(158.0), summary.standard_deviation.map(|sd| sd.round()));
+        assert_eq!(
+            Some(0.263),
+            summary
+                .coefficient_of_variation
+                .map(|cv| (cv * 1000.).round() / 1000.)
+        );
+        assert_eq!(
+            Some(5.),
+            summary.get(&"n".parse::<ReactionTimeStatistic>().unwrap())
+        );
+    }
+
+    #[test]
+    fn reaction_time_summary_all_wrong() {
+        assert_eq!(
+            None,
+            ReactionTimeSummary::from_trials(&[trial(false, 500_000)])
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use crate::Trial;
+
+pub fn block_count(trials: &[Trial]) -> usize {
+    trials.iter().map(|trial| trial.block).max().unwrap_or(0)
+}
+
+pub fn trials_in_block(trials: &[Trial], block: usize) -> Vec<Trial> {
+    trials
+        .iter()
+        .filter(|trial| trial.block == block)
+        .cloned()
+        .collect::<Vec<_>>()
+}
+
+/// The trials in one quarter of the session, numbered from 1, by trial order.
+/// When the trial count isn't a multiple of four the quarters differ in size
+/// by at most one trial.
+pub fn trials_in_quartile(trials: &[Trial], quartile: usize) -> Vec<Trial> {
+    trials[trials.len() * (quartile - 1) / 4..trials.len() * quartile / 4].to_vec()
+}
+
+/// A stretch of the session that summaries can be broken down by.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Period {
+    Block(usize),
+    Quartile(usize),
+}
+
+impl Period {
+    pub fn trials(&self, trials: &[Trial]) -> Vec<Trial> {
+        match self {
+            Period::Block(block) => trials_in_block(trials, *block),
+            Period::Quartile(quartile) => trials_in_quartile(trials, *quartile),
+        }
+    }
+}
+
+impl std::fmt::Display for Period {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Period::Block(block) => write!(f, "Block {}", block),
+            Period::Quartile(quartile) => write!(f, "Quartile {}", quartile),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Period;
+    use crate::{Condition, Sex, Trial};
+
+    fn trial(block: usize, response_time_milliseconds: i64) -> Trial {
+        Trial {
+            correct_response: true,
+            condition: Condition::Happy,
+            sex: Sex::Female,
+            response_time_milliseconds: Some(response_time_milliseconds),
+            button_press_time_microseconds: Some(response_time_milliseconds * 1000),
+            block,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn blocks() {
+        let trials = vec![trial(1, 500), trial(1, 600), trial(2, 700)];
+        assert_eq!(2, crate::time_on_task::block_count(&trials));
+        assert_eq!(
+            vec![trial(2, 700)],
+            crate::time_on_task::trials_in_block(&trials, 2)
+        );
+        assert_eq!(vec![trial(2, 700)], Period::Block(2).trials(&trials));
+    }
+
+    #[test]
+    fn quartiles() {
+        let trials = (1..=10).map(|index| trial(1, index)).collect::<Vec<_>>();
+        assert_eq!(
+            vec![2, 3, 2, 3],
+            (1..=4)
+                .map(|quartile| crate::time_on_task::trials_in_quartile(&trials, quartile).len())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![trial(1, 9), trial(1, 10)],
+            crate::time_on_task::trials_in_quartile(&trials, 4)[1..]
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use crate::statistics::{mean, median, percentile, standard_deviation};
+use crate::{
+    onset_events, stimulus_onset, trial_presses, trial_segments, Event, ReconstructionOptions,
+};
+
+// Onsets further apart than this straddle a break, not one stimulus interval.
+const BREAK_MICROSECONDS: i64 = 10_000_000;
+// Timing outside this many interquartile ranges beyond the quartiles is an
+// outlier...
+const FENCE_INTERQUARTILE_RANGES: f64 = 3.;
+// ...as long as it is also more than a 60 Hz frame from the median, so the
+// near constant latencies of a healthy recording are not all flagged.
+const MINIMUM_FENCE_MILLISECONDS: f64 = 1000. / 60.;
+
+/// Trigger timing of one trial, all in milliseconds.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TrialTiming {
+    /// Visual onset (photodiode) minus the condition trigger; negative when the
+    /// visual came first. `None` when the condition code only arrived
+    /// combined with the visual bit.
+    pub condition_to_visual: Option<f64>,
+    /// Since the previous trial's visual onset, unless a break lies between.
+    pub inter_stimulus_interval: Option<f64>,
+    /// From the stimulus onset reaction times are measured from, as
+    /// `stimulus_onset` finds it, to the first button press.
+    pub response_latency: Option<f64>,
+}
+
+/// Only the decoder and the display lag of `options` are used.
+pub fn trial_timings(events: &[Event], options: &ReconstructionOptions) -> Vec<TrialTiming> {
+    let decoder = &options.decoder;
+    let mut previous_visual_time = None;
+    trial_segments(events, decoder)
+        .iter()
+        .map(|segment| {
+            let (condition, visual) = onset_events(segment, decoder);
+            let (stimulus_time, _) = stimulus_onset(segment, options);
+            let response = trial_presses(segment, decoder).first().copied();
+            let visual_time = visual.map(|event| event.time_microseconds);
+            let milliseconds = |from: i64, to: i64| (to - from) as f64 / 1000.;
+            let timing = TrialTiming {
+                condition_to_visual: condition
+                    .filter(|condition| !decoder.decode(condition.trigger_code).photodiode)
+                    .zip(visual_time)
+                    .map(|(condition, visual_time)| {
+                        milliseconds(condition.time_microseconds, visual_time)
+                    }),
+                inter_stimulus_interval: previous_visual_time
+                    .zip(visual_time)
+                    .filter(|(previous, current)| current - previous <= BREAK_MICROSECONDS)
+                    .map(|(previous, current)| milliseconds(previous, current)),
+                response_latency: response
+                    .map(|response| milliseconds(stimulus_time, response.time_microseconds)),
+            };
+            previous_visual_time = visual_time.or(previous_visual_time);
+            timing
+        })
+        .collect::<Vec<_>>()
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TimingMeasure {
+    ConditionToVisual,
+    InterStimulusInterval,
+    ResponseLatency,
+}
+
+impl TimingMeasure {
+    pub const ALL: [TimingMeasure; 3] = [
+        TimingMeasure::ConditionToVisual,
+        TimingMeasure::InterStimulusInterval,
+        TimingMeasure::ResponseLatency,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimingMeasure::ConditionToVisual => "Condition to Visual (ms)",
+            TimingMeasure::InterStimulusInterval => "Inter-Stimulus Interval (ms)",
+            TimingMeasure::ResponseLatency => "Response Latency (ms)",
+        }
+    }
+
+    pub fn get(&self, timing: &TrialTiming) -> Option<f64> {
+        match self {
+            TimingMeasure::ConditionToVisual => timing.condition_to_visual,
+            TimingMeasure::InterStimulusInterval => timing.inter_stimulus_interval,
+            TimingMeasure::ResponseLatency => timing.response_latency,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TimingDistribution {
+    pub count: usize,
+    pub mean: f64,
+    pub standard_deviation: Option<f64>,
+    pub minimum: f64,
+    pub median: f64,
+    pub maximum: f64,
+    pub lower_fence: f64,
+    pub upper_fence: f64,
+    /// 1-based numbers of the trials outside the fences.
+    pub outlier_trials: Vec<usize>,
+}
+
+/// `None` if no trial has a value for the measure.
+pub fn timing_distribution(
+    timings: &[TrialTiming],
+    measure: TimingMeasure,
+) -> Option<TimingDistribution> {
+    let numbered = timings
+        .iter()
+        .enumerate()
+        .filter_map(|(index, timing)| measure.get(timing).map(|value| (index + 1, value)))
+        .collect::<Vec<_>>();
+    let values = numbered.iter().map(|(_, value)| *value).collect::<Vec<_>>();
+    let median = median(&values)?;
+    let lower_quartile = percentile(&values, 25.)?;
+    let upper_quartile = percentile(&values, 75.)?;
+    let spread = FENCE_INTERQUARTILE_RANGES * (upper_quartile - lower_quartile);
+    let lower_fence = (lower_quartile - spread).min(median - MINIMUM_FENCE_MILLISECONDS);
+    let upper_fence = (upper_quartile + spread).max(median + MINIMUM_FENCE_MILLISECONDS);
+    Some(TimingDistribution {
+        count: values.len(),
+        mean: mean(&values)?,
+        standard_deviation: standard_deviation(&values),
+        minimum: values.iter().copied().fold(f64::INFINITY, f64::min),
+        median,
+        maximum: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        lower_fence,
+        upper_fence,
+        outlier_trials: numbered
+            .iter()
+            .filter(|(_, value)| *value < lower_fence || upper_fence < *value)
+            .map(|(trial, _)| *trial)
+            .collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TimingMeasure;
+    use crate::{reconstruct_trials_with_options, Event, ReconstructionOptions};
+
+    fn event(time_microseconds: i64, trigger_code: i32) -> Event {
+        Event {
+            time_microseconds,
+            trigger_code,
+        }
+    }
+
+    #[test]
+    fn trial_timings() {
+        let timings = super::trial_timings(
+            &[
+                event(1_000_000, 31),
+                event(1_010_000, 4127),
+                event(1_600_000, 256),
+                event(4_000_000, 22),
+                event(4_043_000, 4118),
+                event(6_000_000, 4096),
+                event(30_000_000, 4117),
+                event(30_700_000, 512),
+                event(33_000_000, 4096),
+                event(33_005_000, 23),
+                event(33_500_000, 512),
+            ],
+            &ReconstructionOptions::default(),
+        );
+        assert_eq!(4, timings.len());
+        assert_eq!(Some(10.), timings[0].condition_to_visual);
+        assert_eq!(None, timings[0].inter_stimulus_interval);
+        assert_eq!(Some(590.), timings[0].response_latency);
+        assert_eq!(Some(43.), timings[1].condition_to_visual);
+        assert_eq!(Some(3033.), timings[1].inter_stimulus_interval);
+        assert_eq!(None, timings[1].response_latency);
+        // A combined trigger after a lone visual and a break.
+        assert_eq!(None, timings[2].condition_to_visual);
+        assert_eq!(None, timings[2].inter_stimulus_interval);
+        assert_eq!(Some(700.), timings[2].response_latency);
+        // Visual before the condition trigger.
+        assert_eq!(Some(-5.), timings[3].condition_to_visual);
+        assert_eq!(Some(3000.), timings[3].inter_stimulus_interval);
+        assert_eq!(Some(500.), timings[3].response_latency);
+    }
+
+    #[test]
+    fn response_latency_without_visual() {
+        let events = || {
+            vec![
+                event(1_000_000, 31),
+                event(1_600_000, 256),
+                event(20_000_000, 22),
+                event(20_010_000, 4118),
+                event(20_500_000, 512),
+            ]
+        };
+        let options = ReconstructionOptions {
+            display_lag_microseconds: 20_000,
+            ..Default::default()
+        };
+        let timings = super::trial_timings(&events(), &options);
+        assert_eq!(2, timings.len());
+        assert_eq!(None, timings[0].condition_to_visual);
+        assert_eq!(Some(580.), timings[0].response_latency);
+        assert_eq!(Some(490.), timings[1].response_latency);
+        // The same presses as reaction times are measured to.
+        let trials = reconstruct_trials_with_options(events(), &options);
+        assert_eq!(
+            vec![Some(580), Some(490)],
+            trials
+                .iter()
+                .map(|trial| trial.response_time_milliseconds)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn outliers_flagged() {
+        let mut latencies = [10.; 20];
+        latencies[3] = 11.;
+        latencies[7] = 43.;
+        let timings = latencies
+            .iter()
+            .map(|&latency| super::TrialTiming {
+                condition_to_visual: Some(latency),
+                inter_stimulus_interval: None,
+                response_latency: None,
+            })
+            .collect::<Vec<_>>();
+        let distribution =
+            super::timing_distribution(&timings, TimingMeasure::ConditionToVisual).unwrap();
+        assert_eq!(20, distribution.count);
+        assert_eq!(10., distribution.median);
+        assert_eq!(43., distribution.maximum);
+        assert_eq!(vec![8], distribution.outlier_trials);
+        assert_eq!(
+            None,
+            super::timing_distribution(&timings, TimingMeasure::ResponseLatency)
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use crate::{trial_segments, Event};
+
+// Trigger lines bits can be assigned to in a `TriggerDecoder` description.
+const LINES: [&str; 4] = ["stimulus-bits", "button1", "button2", "photodiode"];
+
+/// One of the two response buttons.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Button {
+    One,
+    Two,
+}
+
+impl Button {
+    pub fn other(self) -> Button {
+        match self {
+            Button::One => Button::Two,
+            Button::Two => Button::One,
+        }
+    }
+}
+
+/// Which bits of a trigger code carry which line. The defaults are this
+/// paradigm's setup: the stimulus code in the low byte, button 1 on bit 8,
+/// button 2 on bit 9 and the photodiode on bit 12.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TriggerDecoder {
+    /// How many low bits hold the stimulus code.
+    pub stimulus_bits: u32,
+    pub button_1_bit: u32,
+    pub button_2_bit: u32,
+    pub photodiode_bit: u32,
+}
+
+impl Default for TriggerDecoder {
+    fn default() -> Self {
+        TriggerDecoder {
+            stimulus_bits: 8,
+            button_1_bit: 8,
+            button_2_bit: 9,
+            photodiode_bit: 12,
+        }
+    }
+}
+
+/// A trigger code split into its lines.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DecodedTrigger {
+    pub stimulus_code: i32,
+    pub button_1: bool,
+    pub button_2: bool,
+    pub photodiode: bool,
+    /// Whatever is set outside the stimulus code and the named lines, left
+    /// in place.
+    pub other_bits: i32,
+}
+
+impl DecodedTrigger {
+    pub fn is_press(&self) -> bool {
+        self.button_1 || self.button_2
+    }
+
+    /// The stimulus code, if no button or other bit arrived with it. The
+    /// photodiode may have.
+    pub fn condition_code(&self) -> Option<i32> {
+        if self.is_press() || self.other_bits != 0 {
+            None
+        } else {
+            Some(self.stimulus_code)
+        }
+    }
+
+    /// Anything besides the buttons and the photodiode.
+    pub fn has_code(&self) -> bool {
+        self.stimulus_code != 0 || self.other_bits != 0
+    }
+
+    /// The button pressed, if exactly one was and nothing else but the
+    /// photodiode came with it.
+    pub fn button(&self) -> Option<Button> {
+        if self.has_code() {
+            return None;
+        }
+        match (self.button_1, self.button_2) {
+            (true, false) => Some(Button::One),
+            (false, true) => Some(Button::Two),
+            _ => None,
+        }
+    }
+
+    /// Both buttons at once, and nothing else but the photodiode.
+    pub fn both_buttons(&self) -> bool {
+        self.button_1 && self.button_2 && !self.has_code()
+    }
+}
+
+impl std::fmt::Display for DecodedTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut lines = Vec::new();
+        if self.stimulus_code != 0 {
+            lines.push(format!("stimulus {}", self.stimulus_code));
+        }
+        if self.button_1 {
+            lines.push("button 1".to_string());
+        }
+        if self.button_2 {
+            lines.push("button 2".to_string());
+        }
+        if self.photodiode {
+            lines.push("photodiode".to_string());
+        }
+        if self.other_bits != 0 {
+            lines.push(format!("other bits {:#06x}", self.other_bits));
+        }
+        if lines.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", lines.join(" + "))
+        }
+    }
+}
+
+impl TriggerDecoder {
+    pub fn decode(&self, trigger_code: i32) -> DecodedTrigger {
+        let stimulus_mask = (1 << self.stimulus_bits) - 1;
+        let button_1_mask = 1 << self.button_1_bit;
+        let button_2_mask = 1 << self.button_2_bit;
+        let photodiode_mask = 1 << self.photodiode_bit;
+        DecodedTrigger {
+            stimulus_code: trigger_code & stimulus_mask,
+            button_1: trigger_code & button_1_mask != 0,
+            button_2: trigger_code & button_2_mask != 0,
+            photodiode: trigger_code & photodiode_mask != 0,
+            other_bits: trigger_code
+                & !(stimulus_mask | button_1_mask | button_2_mask | photodiode_mask),
+        }
+    }
+
+    /// The decoder written back in the form `from_str` reads.
+    pub fn description(&self) -> String {
+        format!(
+            "stimulus-bits={},button1={},button2={},photodiode={}",
+            self.stimulus_bits, self.button_1_bit, self.button_2_bit, self.photodiode_bit
+        )
+    }
+}
+
+/// Parses comma separated `<line>=<bit>` pairs, such as
+/// "button1=0,button2=1,photodiode=15,stimulus-bits=0" for a site with the
+/// buttons on the lowest bits. Lines left out keep their default.
+impl std::str::FromStr for TriggerDecoder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut decoder = TriggerDecoder::default();
+        for pair in s.split(',') {
+            let (line, bit) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected <line>=<bit>: {}", pair.trim()))?;
+            let bit = bit
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|&bit| bit < 31)
+                .ok_or_else(|| format!("invalid bit for {}: {}", line.trim(), bit.trim()))?;
+            match line.trim().to_ascii_lowercase().as_str() {
+                "stimulus-bits" => decoder.stimulus_bits = bit,
+                "button1" => decoder.button_1_bit = bit,
+                "button2" => decoder.button_2_bit = bit,
+                "photodiode" => decoder.photodiode_bit = bit,
+                line => {
+                    return Err(format!(
+                        "unknown trigger line {}, expected one of {}",
+                        line,
+                        LINES.join(", ")
+                    ))
+                }
+            }
+        }
+        let single_bits = [
+            decoder.button_1_bit,
+            decoder.button_2_bit,
+            decoder.photodiode_bit,
+        ];
+        if single_bits.iter().enumerate().any(|(index, bit)| {
+            single_bits[index + 1..].contains(bit) || *bit < decoder.stimulus_bits
+        }) {
+            return Err(format!("trigger lines overlap: {}", decoder.description()));
+        }
+        Ok(decoder)
+    }
+}
+
+/// One event of a recording, decoded for inspection.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DecodedEvent {
+    pub time_microseconds: i64,
+    pub trigger_code: i32,
+    /// 1-based number of the trial the event falls in, `None` before the
+    /// first one.
+    pub trial: Option<usize>,
+    pub decoded: DecodedTrigger,
+}
+
+pub fn decode_events(events: &[Event], decoder: &TriggerDecoder) -> Vec<DecodedEvent> {
+    let segments = trial_segments(events, decoder);
+    let before_first_trial =
+        events.len() - segments.iter().map(|segment| segment.len()).sum::<usize>();
+    let trials = std::iter::repeat_n(None, before_first_trial).chain(
+        segments
+            .iter()
+            .enumerate()
+            .flat_map(|(index, segment)| std::iter::repeat_n(Some(index + 1), segment.len())),
+    );
+    events
+        .iter()
+        .zip(trials)
+        .map(|(event, trial)| DecodedEvent {
+            time_microseconds: event.time_microseconds,
+            trigger_code: event.trigger_code,
+            trial,
+            decoded: decoder.decode(event.trigger_code),
+        })
+        .collect::<Vec<_>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Button, TriggerDecoder};
+    use crate::Event;
+
+    #[test]
+    fn decode_default_lines() {
+        let decoder = TriggerDecoder::default();
+        let decoded = decoder.decode(4118);
+        assert_eq!(22, decoded.stimulus_code);
+        assert!(decoded.photodiode);
+        assert_eq!(Some(22), decoded.condition_code());
+        assert_eq!("stimulus 22 + photodiode", decoded.to_string());
+        assert_eq!(Some(Button::Two), decoder.decode(512 | 4096).button());
+        assert!(decoder.decode(768).both_buttons());
+        assert_eq!(None, decoder.decode(768).button());
+        let garbled = decoder.decode(7936);
+        assert_eq!(0xc00, garbled.other_bits);
+        assert!(!garbled.both_buttons());
+        assert_eq!(None, garbled.condition_code());
+        assert_eq!(
+            "button 1 + button 2 + photodiode + other bits 0x0c00",
+            garbled.to_string()
+        );
+        assert_eq!("none", decoder.decode(0).to_string());
+    }
+
+    #[test]
+    fn parse_site_lines() {
+        let decoder = "button1=0, button2=1, photodiode=15, stimulus-bits=0"
+            .parse::<TriggerDecoder>()
+            .unwrap();
+        let decoded = decoder.decode(1 | (1 << 15));
+        assert_eq!(Some(Button::One), decoded.button());
+        assert!(decoded.photodiode);
+        assert_eq!(
+            decoder,
+            decoder.description().parse::<TriggerDecoder>().unwrap()
+        );
+        assert_eq!(Ok(TriggerDecoder::default()), "photodiode=12".parse());
+        assert!("button1=9".parse::<TriggerDecoder>().is_err());
+        assert!("button1=4".parse::<TriggerDecoder>().is_err());
+        assert!("lamp=3".parse::<TriggerDecoder>().is_err());
+        assert!("photodiode".parse::<TriggerDecoder>().is_err());
+    }
+
+    #[test]
+    fn decode_events_numbers_trials() {
+        let events = [
+            (1_000_000, 64),
+            (5_000_000, 31),
+            (5_010_000, 4127),
+            (5_600_000, 256),
+        ]
+        .into_iter()
+        .map(|(time_microseconds, trigger_code)| Event {
+            time_microseconds,
+            trigger_code,
+        })
+        .collect::<Vec<_>>();
+        let decoded = super::decode_events(&events, &TriggerDecoder::default());
+        assert_eq!(
+            vec![None, Some(1), Some(1), Some(1)],
+            decoded.iter().map(|event| event.trial).collect::<Vec<_>>()
+        );
+        assert_eq!("button 1", decoded[3].decoded.to_string());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::anova::CELLS;
+use crate::statistics::{mean, median, standard_deviation};
+use crate::{trials_matching, Trial};
+
+// Scales the median absolute deviation so it estimates the standard deviation
+// of normally distributed data.
+const MEDIAN_ABSOLUTE_DEVIATION_SCALE: f64 = 1.4826;
+
+/// How correct-response reaction times far from the rest of a cell are
+/// removed before averaging.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub enum Trimming {
+    #[default]
+    None,
+    /// Beyond this many standard deviations from the mean.
+    StandardDeviations(f64),
+    /// Beyond this many scaled median absolute deviations from the median.
+    MedianAbsoluteDeviations(f64),
+}
+
+impl Trimming {
+    /// Removes outlying correct trials, each against the other trials of its
+    /// emotion by face sex cell, so trials pooled over cells are trimmed cell
+    /// by cell. Incorrect trials are kept since they carry no reaction time.
+    pub fn trim(&self, trials: &[Trial]) -> Vec<Trial> {
+        let cell_bounds = CELLS
+            .iter()
+            .map(|(condition, sex)| {
+                self.bounds(&trials_matching(trials, Some(condition), Some(sex)))
+            })
+            .collect::<Vec<_>>();
+        trials
+            .iter()
+            .filter(|trial| {
+                let bounds = CELLS
+                    .iter()
+                    .position(|(condition, sex)| trial.condition == *condition && trial.sex == *sex)
+                    .and_then(|cell| cell_bounds[cell]);
+                match (bounds, trial.response_time_milliseconds) {
+                    (Some((lower, upper)), Some(milliseconds)) if trial.correct_response => {
+                        lower <= milliseconds as f64 && milliseconds as f64 <= upper
+                    }
+                    _ => true,
+                }
+            })
+            .cloned()
+            .collect::<Vec<_>>()
+    }
+
+    pub fn removed_count(&self, trials: &[Trial]) -> usize {
+        trials.len() - self.trim(trials).len()
+    }
+
+    /// The range of reaction times kept among the correct trials of one cell.
+    fn bounds(&self, trials: &[Trial]) -> Option<(f64, f64)> {
+        let reaction_times = trials
+            .iter()
+            .filter(|trial| trial.correct_response)
+            .filter_map(|trial| trial.response_time_milliseconds)
+            .map(|milliseconds| milliseconds as f64)
+            .collect::<Vec<_>>();
+        match self {
+            Trimming::None => None,
+            Trimming::StandardDeviations(cutoff) => mean(&reaction_times)
+                .zip(standard_deviation(&reaction_times))
+                .map(|(mean, sd)| (mean - cutoff * sd, mean + cutoff * sd)),
+            Trimming::MedianAbsoluteDeviations(cutoff) => {
+                median(&reaction_times).and_then(|center| {
+                    let deviations = reaction_times
+                        .iter()
+                        .map(|milliseconds| (milliseconds - center).abs())
+                        .collect::<Vec<_>>();
+                    median(&deviations).map(|deviation| {
+                        let spread = cutoff * MEDIAN_ABSOLUTE_DEVIATION_SCALE * deviation;
+                        (center - spread, center + spread)
+                    })
+                })
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Trimming {
+    type Err = String;
+
+    /// Accepts "none", "sd:<cutoff>" or "mad:<cutoff>".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cutoff = |value: &str| {
+            value
+                .parse::<f64>()
+                .map_err(|_| format!("invalid trimming cutoff: {}", value))
+        };
+        match s.split_once(':') {
+            None if s == "none" => Ok(Trimming::None),
+            Some(("sd", value)) => Ok(Trimming::StandardDeviations(cutoff(value)?)),
+            Some(("mad", value)) => Ok(Trimming::MedianAbsoluteDeviations(cutoff(value)?)),
+            _ => Err(format!("unknown trimming strategy: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Trimming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Trimming::None => write!(f, "none"),
+            Trimming::StandardDeviations(cutoff) => write!(f, "{} SD", cutoff),
+            Trimming::MedianAbsoluteDeviations(cutoff) => write!(f, "{} MAD", cutoff),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Trimming;
+    use crate::{Condition, Sex, Trial};
+
+    fn trial(response_time_milliseconds: Option<i64>) -> Trial {
+        Trial {
+            correct_response: response_time_milliseconds.is_some(),
+            condition: Condition::Angry,
+            sex: Sex::Female,
+            response_time_milliseconds,
+            button_press_time_microseconds: response_time_milliseconds
+                .map(|milliseconds| milliseconds * 1000),
+            ..Default::default()
+        }
+    }
+
+    fn trials() -> Vec<Trial> {
+        [500, 520, 540, 560, 580, 600, 620, 640, 660, 3000]
+            .into_iter()
+            .map(|milliseconds| trial(Some(milliseconds)))
+            .chain([trial(None)])
+            .collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn no_trimming() {
+        assert_eq!(0, Trimming::None.removed_count(&trials()));
+    }
+
+    #[test]
+    fn standard_deviations() {
+        let trimmed = Trimming::StandardDeviations(2.5).trim(&trials());
+        assert_eq!(10, trimmed.len());
+        assert_eq!(Some(580), crate::reaction_time_milliseconds(&trimmed));
+    }
+
+    #[test]
+    fn trimmed_within_cells() {
+        // Slow happy male faces would hide the outlier among the angry
+        // female faces if the pooled trials were trimmed together.
+        let pooled = trials()
+            .into_iter()
+            .chain((0..10).map(|index| Trial {
+                condition: Condition::Happy,
+                sex: Sex::Male,
+                ..trial(Some(2000 + 20 * index))
+            }))
+            .collect::<Vec<_>>();
+        let trimmed = Trimming::StandardDeviations(2.5).trim(&pooled);
+        assert_eq!(20, trimmed.len());
+        assert!(trimmed
+            .iter()
+            .all(|trial| trial.response_time_milliseconds != Some(3000)));
+    }
+
+    #[test]
+    fn median_absolute_deviations() {
+        assert_eq!(
+            2,
+            Trimming::MedianAbsoluteDeviations(1.).removed_count(&trials())
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Ok(Trimming::None), "none".parse::<Trimming>());
+        assert_eq!(
+            Ok(Trimming::StandardDeviations(2.5)),
+            "sd:2.5".parse::<Trimming>()
+        );
+        assert_eq!(
+            Ok(Trimming::MedianAbsoluteDeviations(3.)),
+            "mad:3".parse::<Trimming>()
+        );
+        assert!("iqr:1.5".parse::<Trimming>().is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use crate::anova::CELLS;
+use crate::triggers::TriggerDecoder;
+use crate::{
+    onset_events, parse_events, reconstruct_trials_with_options, trial_segments, Event,
+    ReconstructionOptions, CONDITION_CODES,
+};
+
+const EXPECTED_TRIALS: usize = 240;
+const EXPECTED_TRIALS_PER_CELL: usize = 40;
+// The same trigger again within this long is a duplicate rather than the
+// next trial.
+const DUPLICATE_WITHIN_MICROSECONDS: i64 = 100_000;
+// Response rates below these are worth a warning, or make the recording
+// unusable.
+const WARN_BELOW_RESPONSE_RATE: f64 = 90.;
+const FAIL_BELOW_RESPONSE_RATE: f64 = 50.;
+
+/// Ordered from best to worst.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::Pass => write!(f, "pass"),
+            Status::Warn => write!(f, "warn"),
+            Status::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+/// Data quality of one recording, checked before analysis.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValidationReport {
+    pub trial_count: usize,
+    /// Trials per emotion and face sex, in `anova::CELLS` order.
+    pub condition_counts: [usize; 6],
+    /// Triggers with bits set besides a condition code, the visual onset and
+    /// the two buttons.
+    pub unknown_trigger_count: usize,
+    pub trials_without_visual: usize,
+    pub response_rate_percentage: f64,
+    pub duplicated_trigger_count: usize,
+    pub status: Status,
+    /// Why the status is not `Pass`.
+    pub problems: Vec<String>,
+}
+
+fn duplicated_trigger_count(events: &[Event]) -> usize {
+    events
+        .windows(2)
+        .filter(|window| {
+            window[0].trigger_code == window[1].trigger_code
+                && window[1].time_microseconds - window[0].time_microseconds
+                    < DUPLICATE_WITHIN_MICROSECONDS
+        })
+        .count()
+}
+
+/// Checks a BESA .evt file's contents. Recordings that cannot be split into
+/// trials at all fail without the trial-level checks.
+pub fn validate(input: &str, decoder: &TriggerDecoder) -> ValidationReport {
+    let events = parse_events(input);
+    let is_known = |trigger_code: i32| {
+        let decoded = decoder.decode(trigger_code);
+        decoded.other_bits == 0
+            && (decoded.stimulus_code == 0 || CONDITION_CODES.contains(&decoded.stimulus_code))
+    };
+    let mut report = ValidationReport {
+        trial_count: 0,
+        condition_counts: [0; 6],
+        unknown_trigger_count: events
+            .iter()
+            .filter(|event| !is_known(event.trigger_code))
+            .count(),
+        trials_without_visual: 0,
+        response_rate_percentage: 0.,
+        duplicated_trigger_count: duplicated_trigger_count(&events),
+        status: Status::Pass,
+        problems: Vec::new(),
+    };
+    let segments = trial_segments(&events, decoder);
+    if segments.is_empty() || segments.iter().any(|segment| segment.len() < 2) {
+        report.status = Status::Fail;
+        report
+            .problems
+            .push("triggers could not be split into trials".to_string());
+        return report;
+    }
+    report.trials_without_visual = segments
+        .iter()
+        .filter(|segment| onset_events(segment, decoder).1.is_none())
+        .count();
+    let trials = reconstruct_trials_with_options(
+        events,
+        &ReconstructionOptions {
+            decoder: decoder.clone(),
+            ..Default::default()
+        },
+    );
+    report.trial_count = trials.len();
+    for (count, (condition, sex)) in report.condition_counts.iter_mut().zip(&CELLS) {
+        *count = trials
+            .iter()
+            .filter(|trial| trial.condition == *condition && trial.sex == *sex)
+            .count();
+    }
+    report.response_rate_percentage = 100.
+        * trials
+            .iter()
+            .filter(|trial| trial.button_press_time_microseconds.is_some())
+            .count() as f64
+        / trials.len() as f64;
+    let mut flag = |status: Status, problem: String| {
+        report.status = report.status.max(status);
+        report.problems.push(problem);
+    };
+    if report.trial_count != EXPECTED_TRIALS {
+        flag(
+            Status::Fail,
+            format!(
+                "{} trials instead of {}",
+                report.trial_count, EXPECTED_TRIALS
+            ),
+        );
+    }
+    if report
+        .condition_counts
+        .iter()
+        .any(|&count| count != EXPECTED_TRIALS_PER_CELL)
+    {
+        flag(
+            Status::Warn,
+            format!(
+                "condition counts other than {} per cell",
+                EXPECTED_TRIALS_PER_CELL
+            ),
+        );
+    }
+    if report.unknown_trigger_count > 0 {
+        flag(
+            Status::Warn,
+            format!("{} unknown trigger codes", report.unknown_trigger_count),
+        );
+    }
+    if report.trials_without_visual > 0 {
+        flag(
+            Status::Warn,
+            format!(
+                "{} trials without a visual trigger",
+                report.trials_without_visual
+            ),
+        );
+    }
+    if report.response_rate_percentage < FAIL_BELOW_RESPONSE_RATE {
+        flag(
+            Status::Fail,
+            format!("response rate {:.1}%", report.response_rate_percentage),
+        );
+    } else if report.response_rate_percentage < WARN_BELOW_RESPONSE_RATE {
+        flag(
+            Status::Warn,
+            format!("response rate {:.1}%", report.response_rate_percentage),
+        );
+    }
+    if report.duplicated_trigger_count > 0 {
+        flag(
+            Status::Warn,
+            format!("{} duplicated triggers", report.duplicated_trigger_count),
+        );
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Status;
+    use crate::triggers::TriggerDecoder;
+
+    fn evt(lines: &[(i64, i32)]) -> String {
+        let mut contents = "Tmu         	Code	TriNo	Comnt	Ver-C\n".to_string();
+        for (time, code) in lines {
+            contents.push_str(&format!(
+                "{}        	1	{}	FIFF Trigger: {}\n",
+                time, code, code
+            ));
+        }
+        contents
+    }
+
+    /// 240 trials, 40 per cell, all answered correctly.
+    fn complete_session() -> Vec<(i64, i32)> {
+        let codes = [31, 32, 33, 21, 22, 23];
+        (0..240)
+            .flat_map(|index| {
+                let code = codes[index % 6];
+                let time = 5_000_000 + index as i64 * 3_000_000;
+                let button = if code > 30 { 256 } else { 512 };
+                [
+                    (time, code),
+                    (time + 10_000, code | 4096),
+                    (time + 600_000, button),
+                ]
+            })
+            .collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn complete_session_passes() {
+        let report = super::validate(&evt(&complete_session()), &TriggerDecoder::default());
+        assert_eq!(240, report.trial_count);
+        assert_eq!([40; 6], report.condition_counts);
+        assert_eq!(100., report.response_rate_percentage);
+        assert_eq!(Status::Pass, report.status);
+        assert!(report.problems.is_empty());
+    }
+
+    #[test]
+    fn glitches_warn() {
+        let mut session = complete_session();
+        // A duplicated condition trigger, an unknown code and a missed
+        // response.
+        session.insert(1, (5_001_000, 31));
+        session.push((14_300_000, 64));
+        session.retain(|&(time, _)| time != 8_600_000);
+        session.sort();
+        let report = super::validate(&evt(&session), &TriggerDecoder::default());
+        assert_eq!(240, report.trial_count);
+        assert_eq!(1, report.duplicated_trigger_count);
+        assert_eq!(1, report.unknown_trigger_count);
+        assert_eq!(
+            99.58,
+            (report.response_rate_percentage * 100.).round() / 100.
+        );
+        assert_eq!(Status::Warn, report.status);
+        assert_eq!(2, report.problems.len());
+    }
+
+    #[test]
+    fn short_session_fails() {
+        let session = complete_session()[..300].to_vec();
+        let report = super::validate(&evt(&session), &TriggerDecoder::default());
+        assert_eq!(100, report.trial_count);
+        assert_eq!(Status::Fail, report.status);
+    }
+
+    #[test]
+    fn no_trials_fails() {
+        let report = super::validate(&evt(&[(1_000_000, 4096)]), &TriggerDecoder::default());
+        assert_eq!(Status::Fail, report.status);
+        assert_eq!(0, report.trial_count);
+    }
+}
@@ -0,0 +1,162 @@
+//! Golden-file regression tests. Every .evt recording in tests/golden is
+//! scored with `recording::analyze_recording` as the command line tool does
+//! with its default options, written as trial output rows, and compared trial
+//! by trial with the .csv of the same name next to it.
+//!
+//! To add a recording, anonymise it first: only the triggers, the artifact
+//! segments and a response mapping comment are read, so every other comment
+//! line can be removed. Copy it into tests/golden, run
+//! `GOLDEN_UPDATE=1 cargo test --test golden` to write its expected trials,
+//! and check them by hand before committing both files.
+
+use emotional_faces_recode::{
+    demographics,
+    recording::{self, TRIAL_HEADER},
+    response_mapping::MappingSources,
+    ReconstructionOptions,
+};
+use std::path::{Path, PathBuf};
+
+fn golden_directory() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+fn trial_rows(path: &Path) -> Vec<String> {
+    let contents = std::fs::read_to_string(path)
+        .unwrap_or_else(|why| panic!("couldn't read {}: {}", path.display(), why));
+    let subject = demographics::subject_id(path.to_str().unwrap());
+    recording::analyze_recording(
+        &subject,
+        &contents,
+        &MappingSources::default(),
+        &ReconstructionOptions::default(),
+        false,
+    )
+    .trials
+    .iter()
+    .enumerate()
+    .map(|(index, trial)| recording::trial_row(index + 1, trial))
+    .collect::<Vec<_>>()
+}
+
+/// The columns that differ between two trial lines, as
+/// "Column: expected -> actual".
+fn field_differences(expected: &str, actual: &str) -> String {
+    let expected_fields = expected.split(", ").collect::<Vec<_>>();
+    let actual_fields = actual.split(", ").collect::<Vec<_>>();
+    TRIAL_HEADER
+        .split(", ")
+        .enumerate()
+        .filter_map(|(index, column)| {
+            let expected = expected_fields.get(index).copied().unwrap_or("");
+            let actual = actual_fields.get(index).copied().unwrap_or("");
+            (expected != actual).then(|| format!("{}: {} -> {}", column, expected, actual))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A trial-by-trial report of how `actual` differs from `expected`, or
+/// `None` if they match.
+fn differences(expected: &[String], actual: &[String]) -> Option<String> {
+    let mut report = Vec::new();
+    if expected.len() != actual.len() {
+        report.push(format!(
+            "  {} trials expected, {} reconstructed",
+            expected.len(),
+            actual.len()
+        ));
+    }
+    for index in 0..expected.len().max(actual.len()) {
+        match (expected.get(index), actual.get(index)) {
+            (Some(expected), Some(actual)) if expected != actual => report.push(format!(
+                "  trial {}: {}",
+                index + 1,
+                field_differences(expected, actual)
+            )),
+            (Some(expected), None) => {
+                report.push(format!("  trial {} missing: {}", index + 1, expected))
+            }
+            (None, Some(actual)) => report.push(format!("  trial {} extra: {}", index + 1, actual)),
+            _ => {}
+        }
+    }
+    if report.is_empty() {
+        None
+    } else {
+        Some(report.join("\n"))
+    }
+}
+
+#[test]
+fn golden_recordings() {
+    let update = std::env::var_os("GOLDEN_UPDATE").is_some();
+    let mut recordings = std::fs::read_dir(golden_directory())
+        .expect("couldn't read tests/golden")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "evt"))
+        .collect::<Vec<_>>();
+    recordings.sort();
+    assert!(!recordings.is_empty(), "no recordings in tests/golden");
+    let mut failures = Vec::new();
+    for recording in &recordings {
+        let actual = trial_rows(recording);
+        let expected_path = recording.with_extension("csv");
+        if update {
+            let contents = [vec![TRIAL_HEADER.to_string()], actual].concat().join("\n") + "\n";
+            std::fs::write(&expected_path, contents).unwrap_or_else(|why| {
+                panic!("couldn't write {}: {}", expected_path.display(), why)
+            });
+            continue;
+        }
+        let Ok(contents) = std::fs::read_to_string(&expected_path) else {
+            failures.push(format!(
+                "{}: no expected trials, run with GOLDEN_UPDATE=1 to write them",
+                recording.display()
+            ));
+            continue;
+        };
+        let mut lines = contents.lines();
+        assert_eq!(
+            Some(TRIAL_HEADER),
+            lines.next(),
+            "{} has an outdated header, rewrite it with GOLDEN_UPDATE=1",
+            expected_path.display()
+        );
+        let expected = lines.map(|line| line.to_string()).collect::<Vec<_>>();
+        if let Some(report) = differences(&expected, &actual) {
+            failures.push(format!("{}:\n{}", recording.display(), report));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "reconstruction no longer matches the golden files:\n{}",
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn differences_are_reported_per_trial() {
+    let expected = vec![
+        "1, 1, Angry, Male, true, 512, 5016000, 5528000, false, photodiode, 0, false".to_string(),
+        "2, 1, Happy, Female, true, 600, 8016000, 8616000, false, photodiode, 0, false".to_string(),
+    ];
+    let actual = vec![
+        expected[0].clone(),
+        "2, 1, Happy, Female, false, NaN, 8016000, 8616000, false, photodiode, 0, false"
+            .to_string(),
+        "3, 2, Angry, Female, false, NaN, 11016000, NaN, false, photodiode, 0, false".to_string(),
+    ];
+    assert_eq!(None, differences(&expected, &expected));
+    assert_eq!(
+        Some(
+            "  2 trials expected, 3 reconstructed
+  trial 2: Correct: true -> false, Reaction Time (ms): 600 -> NaN
+  trial 3 extra: 3, 2, Angry, Female, false, NaN, 11016000, NaN, false, photodiode, 0, false"
+                .to_string()
+        ),
+        differences(&expected, &actual)
+    );
+}